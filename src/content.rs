@@ -1,19 +1,57 @@
 use std::{
     borrow::Cow,
     ffi::{CStr, OsStr},
+    fs,
     path::Path,
+    time::SystemTime,
 };
 
-use bevy::ecs::component::Component;
+use bevy::ecs::{change_detection::DetectChangesMut as _, component::Component, system::Query};
 
+/// Stuff for collections of pages, such as a blog.
+pub mod collection;
+/// Stuff for site data files.
+pub mod data;
+/// Stuff for diagrams drawn with typst.
+pub mod diagram;
 /// Stuff for rendering djot.
 pub mod djot;
+/// Stuff for bundling collections and series into EPUBs.
+pub mod epub;
+/// Stuff for generating feeds.
+pub mod feed;
 /// process front matter.
 pub mod front_matter;
+/// Stuff for generating `head` tags.
+pub mod head;
+/// Stuff for publishing the revision history of pages.
+pub mod history;
+/// Stuff for the layout of documents.
+pub mod layout;
+/// Stuff for generating listings, such as the home page and archive.
+pub mod listing;
 /// Stuff for handling math.
 pub mod math;
+/// Stuff for numbering figures and the like, and referencing them.
+pub mod numbering;
+/// Stuff for writing output.
+pub mod output;
 /// Stuff for pages.
 pub mod page;
+/// Stuff for exporting pages to PDF.
+pub mod pdf;
+/// Stuff for generating `robots.txt`.
+pub mod robots;
+/// Stuff for routes.
+pub mod route;
+/// Stuff for generating sitemaps.
+pub mod sitemap;
+/// Stuff for taxonomies, such as tags.
+pub mod taxonomy;
+/// Stuff for tables of contents.
+pub mod toc;
+/// Stuff for pages written in typst.
+pub mod typ;
 
 /// Trait for types that can be used with [`Input`] or [`Output`].
 pub trait Content: 'static {
@@ -57,3 +95,31 @@ pub struct Output<C: Content + ?Sized>(pub C::Output);
 /// The contents of some entity.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Contents(pub String);
+
+/// When the file an [`Input<Path>`] was read from was last modified, as of
+/// when it was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct InputModified(pub SystemTime);
+
+impl InputModified {
+    /// Returns when the file at `path` was last modified, if that's known.
+    #[must_use]
+    pub fn of(path: &Path) -> Option<InputModified> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(InputModified)
+    }
+}
+
+/// Mark every input that has been modified since it was read as changed, so
+/// that it's read again, such as while watching the site.
+///
+/// Files that are added or removed aren't noticed.
+pub fn watch(mut query: Query<(&mut Input<Path>, &InputModified)>) {
+    for (mut input, &modified) in &mut query {
+        if InputModified::of(&input.0).is_some_and(|now| now != modified) {
+            input.set_changed();
+        }
+    }
+}
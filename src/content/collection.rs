@@ -0,0 +1,271 @@
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, anyhow};
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, With},
+    system::{Commands, Query},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bevy::EntityCommandsExt as _,
+    content::{
+        Input, InputModified,
+        front_matter::FrontMatter,
+        layout::Summary,
+        page::{Description, Page, PageOffset, Title},
+        route::Route,
+    },
+};
+
+/// The name of the file that configures a collection, and holds its introduction.
+pub const INDEX: &str = "_index.dj";
+
+/// The name of the file that configures a collection, without an introduction.
+pub const CONFIG: &str = "_collection.toml";
+
+/// The order pages within a collection are sorted in.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// Newest pages first.
+    #[default]
+    Newest,
+    /// Oldest pages first.
+    Oldest,
+    /// Alphabetically by title.
+    Title,
+}
+
+impl Sort {
+    /// Compare two pages according to this order.
+    #[must_use]
+    pub fn compare(self, lhs: &Summary, rhs: &Summary) -> Ordering {
+        let by_title = || lhs.title.cmp(rhs.title);
+
+        match self {
+            // NOTE: Undated pages are always listed last.
+            Sort::Newest => rhs.created.cmp(&lhs.created).then_with(by_title),
+            Sort::Oldest => match (lhs.created, rhs.created) {
+                (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+                (Some(..), None) => Ordering::Less,
+                (None, Some(..)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(by_title),
+            Sort::Title => by_title(),
+        }
+    }
+}
+
+/// The settings of a collection.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, Component)]
+#[serde(default)]
+pub struct CollectionConfig {
+    /// The order pages are listed in.
+    pub sort: Sort,
+    /// The pattern of the routes of pages within the collection, such as
+    /// `/blog/{year}/{slug}/`.
+    ///
+    /// Supports the `{slug}`, `{year}`, `{month}` and `{day}` placeholders,
+    /// as well as `{collection}` for the route of the collection itself.
+    pub url: String,
+    /// The layout pages within the collection use by default.
+    pub layout: Option<String>,
+    /// The layout the listings of the collection are rendered with, falling
+    /// back to [`Site::listing_layout`](crate::site::Site::listing_layout).
+    pub listing_layout: Option<String>,
+    /// Whether pages within the collection appear in feeds.
+    pub feed: bool,
+    /// The amount of pages per page of listings.
+    pub paginate: usize,
+    /// Whether the pages within the collection are bundled into an EPUB.
+    pub epub: bool,
+}
+
+impl Default for CollectionConfig {
+    fn default() -> Self {
+        CollectionConfig {
+            sort: Sort::default(),
+            url: "{collection}/{slug}".into(),
+            layout: None,
+            listing_layout: None,
+            feed: true,
+            paginate: 10,
+            epub: false,
+        }
+    }
+}
+
+/// A struct for storing the metadata for collections.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize)]
+pub struct CollectionMatter {
+    /// The title of the collection.
+    #[serde(default)]
+    pub title: String,
+    /// The description of the collection.
+    #[serde(default)]
+    pub description: String,
+    /// The route of the collection, defaulting to the name of its directory.
+    #[serde(default)]
+    pub route: Option<String>,
+    /// The settings of the collection.
+    #[serde(flatten)]
+    pub config: CollectionConfig,
+}
+
+/// The directory a collection is made up of.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct CollectionDir(pub PathBuf);
+
+/// Relates a page to the collection it's in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+#[relationship(relationship_target = CollectionPages)]
+pub struct Collection(pub Entity);
+
+/// The pages within a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+#[relationship_target(relationship = Collection)]
+pub struct CollectionPages(Vec<Entity>);
+
+/// Find every directory configured as a collection.
+pub fn find(mut commands: Commands) -> Result<()> {
+    fn find_collections_inner(root: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+        let mut read_dir =
+            std::fs::read_dir(root).with_context(|| format!("reading {:?}", root.display()))?;
+
+        while let Some(entry) = read_dir.next().transpose()? {
+            let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .with_context(|| format!("getting metadata for {:?}", root.display()))?;
+
+            if file_type.is_dir() {
+                find_collections_inner(&path, paths)?;
+            } else if file_type.is_file()
+                && (entry.file_name() == INDEX || entry.file_name() == CONFIG)
+            {
+                paths.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut file_paths = Vec::new();
+
+    find_collections_inner(Path::new("."), &mut file_paths)?;
+
+    for path in file_paths {
+        let dir = path.parent().unwrap_or(Path::new(".")).to_owned();
+
+        if path.ends_with(INDEX) && dir.join(CONFIG).exists() {
+            return Err(
+                anyhow!("{:?} has both an {INDEX:?} and a {CONFIG:?}", dir.display()).into(),
+            );
+        }
+
+        commands.spawn((CollectionDir(dir), Input::<Path>(path.into())));
+    }
+
+    Ok(())
+}
+
+/// Load the settings of each collection, from either its `_collection.toml` or
+/// the front matter of its `_index.dj`.
+pub fn load(
+    query: Query<(Entity, &Input<Path>, &CollectionDir), Changed<Input<Path>>>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (entity, Input(path), CollectionDir(dir)) in query {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("reading {:?}", path.display()))?;
+
+        let (matter, page_offset) = if path.ends_with(CONFIG) {
+            let matter: CollectionMatter = toml::from_str(&contents)
+                .with_context(|| format!("parsing {:?} as toml", path.display()))?;
+
+            (matter, None)
+        } else {
+            let front_matter = FrontMatter::parse(&contents).ok();
+            let page_offset = match &front_matter {
+                Some(front_matter) => contents.len() - front_matter.rest.len(),
+                None => 0,
+            };
+
+            let matter: CollectionMatter = match &front_matter {
+                None => Default::default(),
+                Some(FrontMatter {
+                    language: Some("toml") | None,
+                    content,
+                    ..
+                }) => toml::from_str(content)
+                    .with_context(|| format!("parsing {:?} as toml", path.display()))?,
+                Some(FrontMatter {
+                    language: Some(language),
+                    ..
+                }) => {
+                    return Err(
+                        anyhow!("unknown language, {language:?} in {:?}", path.display()).into(),
+                    );
+                }
+            };
+
+            (matter, Some(page_offset))
+        };
+
+        let CollectionMatter {
+            title,
+            description,
+            route,
+            config,
+        } = matter;
+
+        let route = match route {
+            Some(route) => Route::new(route.split('/')),
+            None => Route::new(dir.file_name().and_then(|name| name.to_str())),
+        };
+
+        let mut entity = commands.entity(entity);
+
+        entity
+            .insert((Title(title), Description(description), route, config))
+            .modify(InputModified::of(path));
+
+        // NOTE: The introduction of a collection is parsed like any other page.
+        if let Some(page_offset) = page_offset {
+            entity.insert((Input::<String>(contents), PageOffset(page_offset)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Relate every page to the innermost collection it is within.
+pub fn assign(
+    pages: Query<(Entity, &Input<Path>), (With<Page>, Changed<Input<Path>>)>,
+    collections: Query<(Entity, &CollectionDir)>,
+    mut commands: Commands,
+) {
+    for (entity, Input(path)) in pages {
+        let collection = path.ancestors().skip(1).find_map(|ancestor| {
+            collections
+                .iter()
+                .find(|(_, CollectionDir(dir))| dir == ancestor)
+                .map(|(collection, _)| collection)
+        });
+
+        match collection {
+            Some(collection) => commands.entity(entity).insert(Collection(collection)),
+            None => commands.entity(entity).remove::<Collection>(),
+        };
+    }
+}
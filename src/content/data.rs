@@ -0,0 +1,94 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, With},
+    system::{Commands, Query},
+};
+
+use crate::{
+    bevy::EntityCommandsExt as _,
+    content::{Input, InputModified},
+    typst::files::{FileData, TypstPath},
+};
+
+/// The directory site data files are read from.
+pub const DIR: &str = "data";
+
+/// Marker component for site data files, such as `data/links.toml`.
+///
+/// These aren't published themselves, but are exposed to typst under `/@data`,
+/// see [`TypstPath`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct DataFile;
+
+/// Find every file within the data directory.
+pub fn find(mut commands: Commands) -> Result<()> {
+    fn find_data_inner(root: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+        let mut read_dir =
+            std::fs::read_dir(root).with_context(|| format!("reading {:?}", root.display()))?;
+
+        while let Some(entry) = read_dir.next().transpose()? {
+            let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .with_context(|| format!("getting metadata for {:?}", root.display()))?;
+
+            if file_type.is_dir() {
+                find_data_inner(&path, paths)?;
+            } else if file_type.is_file() {
+                paths.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    let root = Path::new(DIR);
+
+    if !root.is_dir() {
+        return Ok(());
+    }
+
+    let mut file_paths = Vec::new();
+
+    find_data_inner(root, &mut file_paths)?;
+
+    for path in file_paths {
+        commands.spawn((DataFile, Input::<Path>(path.into())));
+    }
+
+    Ok(())
+}
+
+/// Read every data file, exposing it to typst.
+pub fn read(
+    query: Query<(Entity, &Input<Path>), (With<DataFile>, Changed<Input<Path>>)>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (entity, Input(path)) in query {
+        let contents =
+            std::fs::read(path).with_context(|| format!("reading {:?}", path.display()))?;
+
+        let relative = path.strip_prefix(DIR).unwrap_or(path);
+        let mut virtual_path = String::from("/@data");
+
+        for component in relative.components() {
+            virtual_path.push('/');
+            virtual_path.push_str(&component.as_os_str().to_string_lossy());
+        }
+
+        commands
+            .entity(entity)
+            .insert((TypstPath(virtual_path), FileData::new(Cow::Owned(contents))))
+            .modify(InputModified::of(path));
+    }
+
+    Ok(())
+}
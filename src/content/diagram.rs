@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use bevy::ecs::{
+    change_detection::{DetectChanges as _, Ref},
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, Or},
+    system::{Commands, Query, Res, ResMut},
+};
+use jotdown::{AttributeKind, Attributes, Container, Event};
+use typst::diag::Warned;
+
+use crate::{
+    content::{
+        Input,
+        djot::{DjotEvents, DjotOffsets},
+        math::Svg,
+        page::PageOffset,
+    },
+    typst::{
+        diagnostic::{Diagnostics, Origin},
+        inputs::Inputs,
+        world::{Compiled, TypstWorld},
+    },
+};
+
+/// The typst shared by every diagram within a page, as set by the `preamble`
+/// key in its front matter.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Preamble(pub String);
+
+/// Relates a diagram to the page it's within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+#[relationship(relationship_target = Diagrams)]
+pub struct DiagramOf(pub Entity);
+
+/// The diagrams within a page.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+#[relationship_target(relationship = DiagramOf, linked_spawn)]
+pub struct Diagrams(Vec<Entity>);
+
+/// The position of the event that starts a diagram within its page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct DiagramPosition(pub usize);
+
+/// The typst source of a diagram.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct Diagram {
+    /// The source, including the page setup and preamble.
+    pub source: String,
+    /// Where the code of the diagram starts within `source`.
+    pub start: usize,
+    /// Where the code of the diagram starts within its page, relative to its
+    /// [`PageOffset`].
+    pub offset: usize,
+}
+
+/// Returns whether a container is a diagram.
+///
+/// Diagrams are either `=typst` raw blocks, or `typst` code blocks with the
+/// `render` class, such as:
+///
+/// ````djot
+/// {.render}
+/// ``` typst
+/// #circle(radius: 1cm)
+/// ```
+/// ````
+#[must_use]
+pub fn is_diagram(container: &Container<'static>, attributes: &Attributes<'static>) -> bool {
+    match container {
+        Container::RawBlock { format: "typst" } => true,
+        Container::CodeBlock { language: "typst" } => attributes.iter().any(|(kind, value)| {
+            matches!(kind, AttributeKind::Class) && value.parts().collect::<String>() == "render"
+        }),
+        _ => false,
+    }
+}
+
+/// Spawn an entity for every diagram within each page.
+pub fn extract(
+    query: Query<
+        (Entity, &DjotEvents, &DjotOffsets, Option<&Preamble>),
+        Or<(Changed<DjotEvents>, Changed<Preamble>)>,
+    >,
+    mut commands: Commands,
+) {
+    for (page, DjotEvents(events), DjotOffsets(offsets), preamble) in query {
+        commands.entity(page).despawn_related::<Diagrams>();
+
+        for (position, event) in events.iter().enumerate() {
+            let Event::Start(container, attributes) = event else {
+                continue;
+            };
+
+            if !is_diagram(container, attributes) {
+                continue;
+            }
+
+            let mut source =
+                String::from("#set page(width: auto, height: auto, margin: 0pt, fill: none)\n");
+
+            if let Some(Preamble(preamble)) = preamble {
+                source.push_str(preamble);
+                source.push('\n');
+            }
+
+            let start = source.len();
+
+            for event in &events[position + 1..] {
+                match event {
+                    Event::Str(s) => source.push_str(s),
+                    Event::End(..) => break,
+                    _ => {}
+                }
+            }
+
+            let offset = offsets
+                .get(position + 1)
+                .or_else(|| offsets.get(position))
+                .map_or(0, |range| range.start);
+
+            commands.spawn((
+                DiagramOf(page),
+                DiagramPosition(position),
+                Diagram {
+                    source,
+                    start,
+                    offset,
+                },
+            ));
+        }
+    }
+}
+
+/// Render every diagram to SVG with typst, reporting any diagnostics at the
+/// code within its page.
+pub fn render(
+    query: Query<(Entity, &DiagramOf, Ref<Diagram>)>,
+    pages: Query<(&Input<Path>, &Input<String>, &PageOffset, Ref<Inputs>)>,
+    world: Res<TypstWorld>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (entity, &DiagramOf(page), diagram) in query {
+        let (Input(path), Input(contents), &PageOffset(page_offset), inputs) = pages.get(page)?;
+
+        if !diagram.is_changed() && !inputs.is_changed() {
+            continue;
+        }
+
+        let main = TypstWorld::source("/diagram.typ", diagram.source.clone());
+        let Compiled {
+            warned: Warned { output, warnings },
+            dependencies,
+        } = world.compile_svg(&main, &inputs);
+
+        let origin = Origin {
+            path,
+            contents,
+            start: diagram.start,
+            offset: page_offset + diagram.offset,
+        };
+
+        diagnostics.report(&warnings, &main, origin);
+        commands.entity(entity).insert(dependencies);
+
+        match output {
+            Ok(svg) => {
+                commands.entity(entity).insert(Svg(svg));
+            }
+            Err(errors) => {
+                diagnostics.report(&errors, &main, origin);
+                commands.entity(entity).remove::<Svg>();
+            }
+        }
+    }
+
+    Ok(())
+}
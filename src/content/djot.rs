@@ -1,36 +1,281 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::Path,
+};
+
 use bevy::ecs::{
     component::Component,
     entity::Entity,
-    query::{Changed, With},
-    system::{Commands, Query},
+    lifecycle::RemovedComponents,
+    query::{Changed, Or, With, Without},
+    system::{Commands, Query, Res},
 };
 use jotdown::{Event, Parser};
+use serde::{Deserialize, Serialize};
 
-use crate::content::{
-    Input,
-    page::{Page, PageOffset},
+use crate::{
+    content::{
+        Input,
+        diagram::{DiagramOf, DiagramPosition, Diagrams},
+        math::{MathMl, MathOf, MathPosition, MathSpans, Svg},
+        numbering::Labels,
+        page::{Body, PageOffset},
+        typ::TypstPage,
+    },
+    site::Site,
 };
 
+pub mod html;
+pub mod ids;
 pub mod to_static;
+pub mod to_typst;
+
+/// How the footnotes of a page are rendered, as set by the `footnotes` key in
+/// its front matter.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Component,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Footnotes {
+    /// A list of notes at the end of the page, linking back to their references.
+    #[default]
+    Endnotes,
+    /// Notes in the margin next to their references, which are toggled on
+    /// narrow screens.
+    Sidenotes,
+}
 
 /// A list of djot events so that we don't need to reparse events when processing a page.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
 pub struct DjotEvents(pub Vec<Event<'static>>);
 
+/// The range of the source each of the [`DjotEvents`] of a page was parsed
+/// from, relative to its [`PageOffset`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+pub struct DjotOffsets(pub Vec<Range<usize>>);
+
+/// Returns the position of the event that ends the container started at
+/// `start`, or the end of `events` if it is never ended.
+#[must_use]
+pub fn end(events: &[Event<'static>], start: usize) -> usize {
+    let mut depth = 0usize;
+
+    for (position, event) in events.iter().enumerate().skip(start + 1) {
+        match event {
+            Event::Start(..) => depth += 1,
+            Event::End(..) if depth == 0 => return position,
+            Event::End(..) => depth -= 1,
+            _ => {}
+        }
+    }
+
+    events.len()
+}
+
+/// Returns the plain text an event stands for, if any.
+#[must_use]
+pub fn plain_text<'a>(event: &'a Event<'static>) -> Option<&'a str> {
+    Some(match event {
+        Event::Str(s) | Event::Symbol(s) => s,
+        Event::LeftSingleQuote => "‘",
+        Event::RightSingleQuote => "’",
+        Event::LeftDoubleQuote => "“",
+        Event::RightDoubleQuote => "”",
+        Event::Ellipsis => "…",
+        Event::EnDash => "–",
+        Event::EmDash => "—",
+        Event::NonBreakingSpace => "\u{a0}",
+        Event::Softbreak | Event::Hardbreak => "\n",
+        _ => return None,
+    })
+}
+
+/// A walk over a list of djot events, as done by the renderer and the
+/// transpiler.
+#[derive(Debug, Clone)]
+pub struct Cursor<'e> {
+    /// The events being walked over.
+    pub events: &'e [Event<'static>],
+    /// The position of the next event.
+    pub position: usize,
+    /// The position to stop at.
+    pub end: usize,
+}
+
+impl<'e> Cursor<'e> {
+    /// Walk over every event within `events`.
+    #[must_use]
+    pub fn new(events: &'e [Event<'static>]) -> Cursor<'e> {
+        Cursor {
+            events,
+            position: 0,
+            end: events.len(),
+        }
+    }
+
+    /// Returns the next event, without advancing the cursor.
+    #[must_use]
+    pub fn peek(&self) -> Option<&'e Event<'static>> {
+        self.events[..self.end].get(self.position)
+    }
+
+    /// Skip every event up to and including the end of the container whose
+    /// start was just consumed.
+    pub fn skip_container(&mut self) {
+        self.position = end(self.events, self.position - 1) + 1;
+    }
+
+    /// Collect the text within the container whose start was just consumed,
+    /// consuming it.
+    pub fn text_content(&mut self) -> String {
+        let start = self.position - 1;
+        let end = end(self.events, start);
+        let text = self.events[start + 1..end]
+            .iter()
+            .filter_map(plain_text)
+            .collect();
+
+        self.position = end + 1;
+
+        text
+    }
+}
+
+impl<'e> Iterator for Cursor<'e> {
+    type Item = &'e Event<'static>;
+
+    fn next(&mut self) -> Option<&'e Event<'static>> {
+        let event = self.peek()?;
+
+        self.position += 1;
+
+        Some(event)
+    }
+}
+
+/// Parse the djot of every page, as well as the introductions of collections.
 pub fn parse_events(
-    query: Query<(Entity, &Input<String>, &PageOffset), (With<Page>, Changed<Input<String>>)>,
+    query: Query<
+        (Entity, &Input<String>, &Input<Path>, &PageOffset),
+        (Without<TypstPage>, Changed<Input<String>>),
+    >,
     mut commands: Commands,
 ) {
-    for (entity, Input(contents), &PageOffset(offset)) in query {
+    for (entity, Input(contents), Input(path), &PageOffset(offset)) in query {
         let Some(contents) = contents.get(offset..) else {
             continue;
         };
 
-        let events = Parser::new(contents)
-            .skip_while(|event| matches!(event, Event::Blankline))
-            .map(to_static::event)
-            .collect();
+        let (mut events, offsets): (Vec<_>, Vec<_>) = Parser::new(contents)
+            .into_offset_iter()
+            .skip_while(|(event, _)| matches!(event, Event::Blankline))
+            .map(|(event, range)| (to_static::event(event), range))
+            .unzip();
+
+        ids::dedupe(&mut events, path);
+
+        commands
+            .entity(entity)
+            .insert((DjotEvents(events), DjotOffsets(offsets)));
+    }
+}
+
+/// Render the djot of every page to HTML.
+///
+/// Pages are rendered again whenever any of their math or diagrams has been
+/// rendered again, or failed to render, such as after a file they depend on
+/// changed.
+#[expect(clippy::too_many_arguments)]
+pub fn render(
+    changed: Query<
+        Entity,
+        (
+            With<DjotEvents>,
+            Or<(
+                Changed<DjotEvents>,
+                Changed<Footnotes>,
+                Changed<Labels>,
+                Changed<MathSpans>,
+                Changed<Diagrams>,
+            )>,
+        ),
+    >,
+    pages: Query<(&DjotEvents, Option<&Footnotes>, Option<&Labels>)>,
+    changed_spans: Query<&MathOf, Or<(Changed<Svg>, Changed<MathMl>)>>,
+    changed_diagrams: Query<&DiagramOf, Changed<Svg>>,
+    mut removed: RemovedComponents<Svg>,
+    spans: Query<(&MathOf, &MathPosition, Option<&Svg>, Option<&MathMl>)>,
+    diagrams: Query<(&DiagramOf, &DiagramPosition, &Svg)>,
+    owners: Query<(Option<&MathOf>, Option<&DiagramOf>)>,
+    site: Res<Site>,
+    mut commands: Commands,
+) {
+    let mut stale: HashSet<Entity> = changed.iter().collect();
+
+    stale.extend(changed_spans.iter().map(|&MathOf(page)| page));
+    stale.extend(changed_diagrams.iter().map(|&DiagramOf(page)| page));
+
+    // NOTE: Spans that have been despawned altogether are noticed through the
+    //       `MathSpans` or `Diagrams` of their page instead.
+    for entity in removed.read() {
+        if let Ok((math, diagram)) = owners.get(entity) {
+            stale.extend(math.map(|&MathOf(page)| page));
+            stale.extend(diagram.map(|&DiagramOf(page)| page));
+        }
+    }
+
+    if stale.is_empty() {
+        return;
+    }
+
+    let no_labels = Labels::default();
+    let mut math: HashMap<Entity, HashMap<usize, html::RenderedMath>> = HashMap::new();
+
+    for (&MathOf(page), &MathPosition(position), svg, mathml) in &spans {
+        let rendered = html::RenderedMath {
+            svg: svg.map(|Svg(svg)| svg.as_str()),
+            mathml: mathml.map(|MathMl(mathml)| mathml.as_str()),
+        };
+
+        math.entry(page).or_default().insert(position, rendered);
+    }
+
+    let mut figures: HashMap<Entity, HashMap<usize, &str>> = HashMap::new();
+
+    for (&DiagramOf(page), &DiagramPosition(position), Svg(svg)) in &diagrams {
+        figures.entry(page).or_default().insert(position, svg);
+    }
+
+    let none = HashMap::new();
+    let no_math = HashMap::new();
+
+    for entity in stale {
+        let Ok((DjotEvents(events), footnotes, labels)) = pages.get(entity) else {
+            continue;
+        };
+
+        let options = html::Options {
+            footnotes: footnotes.copied().unwrap_or_default(),
+            site: &site,
+            labels: labels.unwrap_or(&no_labels),
+            math: math.get(&entity).unwrap_or(&no_math),
+            diagrams: figures.get(&entity).unwrap_or(&none),
+        };
 
-        commands.entity(entity).insert(DjotEvents(events));
+        commands
+            .entity(entity)
+            .insert(Body(html::render(events, options)));
     }
 }
@@ -0,0 +1,978 @@
+//! Rendering of djot events to HTML.
+//!
+//! We don't use `jotdown`'s own renderer, as we want to be able to extend the
+//! output with our own semantics. The generated markup is also kept XHTML
+//! compatible (void elements are self closing, no named entities), so that
+//! it may be embedded in feeds and the like.
+
+use std::{borrow::Cow, collections::HashMap, mem, ops::Range};
+
+use jotdown::{
+    Alignment, AttributeKind, Attributes, Container, Event, LinkType, ListKind,
+    OrderedListNumbering,
+};
+
+use crate::{
+    content::{
+        djot::{self, Cursor, Footnotes, plain_text},
+        numbering::{self, Kind, Label, Labels},
+    },
+    site::{Callout, Site},
+    util,
+};
+
+/// Options for rendering djot.
+#[derive(Debug, Clone, Copy)]
+pub struct Options<'a> {
+    /// How footnotes are rendered.
+    pub footnotes: Footnotes,
+    /// The site, which configures callouts.
+    pub site: &'a Site,
+    /// The labels of everything numbered.
+    pub labels: &'a Labels,
+    /// The math rendered ahead of time, keyed by the position of the event
+    /// that starts it.
+    pub math: &'a HashMap<usize, RenderedMath<'a>>,
+    /// The diagrams rendered to SVG, keyed by the position of the event that
+    /// starts them.
+    pub diagrams: &'a HashMap<usize, &'a str>,
+}
+
+/// Math rendered ahead of time, to SVG, MathML or both.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderedMath<'a> {
+    pub svg: Option<&'a str>,
+    pub mathml: Option<&'a str>,
+}
+
+/// Render a list of djot events to HTML.
+#[must_use]
+pub fn render(events: &[Event<'static>], options: Options) -> String {
+    let mut renderer = Renderer::new(events, options);
+
+    renderer.run();
+    renderer.endnotes();
+    renderer.out
+}
+
+/// A renderer that walks over a list of djot events.
+#[derive(Debug)]
+struct Renderer<'e> {
+    /// Where we are within the events.
+    cursor: Cursor<'e>,
+    /// Whether the innermost block container is a tight list.
+    tight: Vec<bool>,
+    /// How the events are rendered.
+    options: Options<'e>,
+    /// The closing tags of the divs currently open.
+    divs: Vec<Cow<'static, str>>,
+    /// The labels of the figures whose captions are the paragraphs starting
+    /// at these positions.
+    captions: HashMap<usize, Label>,
+    /// Whether the current paragraph is a caption.
+    caption: bool,
+    /// The labels of the tables currently open.
+    tables: Vec<Option<Label>>,
+    /// The range of events within each footnote, keyed by its label.
+    definitions: HashMap<&'e str, Range<usize>>,
+    /// The labels of the footnotes referenced so far, in order of their
+    /// first reference.
+    referenced: Vec<&'e str>,
+    /// The amount of footnote references rendered so far.
+    references: usize,
+    /// The labels of the sidenotes currently being rendered, so that those
+    /// that reference themselves don't do so forever.
+    sidenotes: Vec<&'e str>,
+    /// The rendered output.
+    out: String,
+}
+
+impl<'e> Renderer<'e> {
+    fn new(events: &'e [Event<'static>], options: Options<'e>) -> Renderer<'e> {
+        let mut definitions = HashMap::new();
+
+        for (start, event) in events.iter().enumerate() {
+            if let Event::Start(Container::Footnote { label }, _) = event {
+                definitions.insert(*label, start + 1..djot::end(events, start));
+            }
+        }
+
+        Renderer {
+            cursor: Cursor::new(events),
+            tight: Vec::new(),
+            options,
+            divs: Vec::new(),
+            captions: HashMap::new(),
+            caption: false,
+            tables: Vec::new(),
+            definitions,
+            referenced: Vec::new(),
+            references: 0,
+            sidenotes: Vec::new(),
+            out: String::new(),
+        }
+    }
+
+    /// Render the events within `range`, returning to where we were afterwards.
+    fn run_range(&mut self, range: Range<usize>) {
+        let position = mem::replace(&mut self.cursor.position, range.start);
+        let end = mem::replace(&mut self.cursor.end, range.end);
+
+        self.run();
+
+        self.cursor.position = position;
+        self.cursor.end = end;
+    }
+
+    /// Render a reference to the footnote labelled `label`.
+    fn footnote_reference(&mut self, label: &'e str) {
+        let (number, first) = match self.referenced.iter().position(|other| *other == label) {
+            Some(index) => (index + 1, false),
+            None => {
+                self.referenced.push(label);
+                (self.referenced.len(), true)
+            }
+        };
+
+        self.references += 1;
+
+        if self.sidenotes.contains(&label) {
+            self.out
+                .push_str(&format!(r#"<span class="sidenote-number">{number}</span>"#));
+            return;
+        }
+
+        if self.is_endnote(label) {
+            self.out.push_str("<a");
+
+            if first {
+                self.out.push_str(&format!(r#" id="fnref{number}""#));
+            }
+
+            self.out.push_str(&format!(
+                r##" href="#fn{number}" role="doc-noteref"><sup>{number}</sup></a>"##
+            ));
+
+            return;
+        }
+
+        let id = format!("sn-{}", self.references);
+
+        self.out.push_str(&format!(
+            r#"<label for="{id}" class="margin-toggle sidenote-number">{number}</label>"#
+        ));
+        self.out.push_str(&format!(
+            r#"<input type="checkbox" id="{id}" class="margin-toggle" />"#
+        ));
+        self.out.push_str(&format!(
+            r#"<span class="sidenote" role="note"><span class="sidenote-number">{number}</span> "#
+        ));
+
+        // NOTE: Sidenotes are inline, so their paragraph is rendered as if it
+        //       were within a tight list.
+        if let Some(range) = self.definitions.get(label).cloned() {
+            self.sidenotes.push(label);
+            self.tight.push(true);
+            self.run_range(range);
+            self.tight.pop();
+            self.sidenotes.pop();
+        }
+
+        self.out.push_str("</span>");
+    }
+
+    /// Returns whether the footnote labelled `label` is rendered as an
+    /// endnote.
+    ///
+    /// Sidenotes are rendered within a `<span>`, so footnotes made up of
+    /// anything but a single paragraph are rendered as endnotes regardless.
+    fn is_endnote(&self, label: &str) -> bool {
+        if self.options.footnotes == Footnotes::Endnotes {
+            return true;
+        }
+
+        let Some(range) = self.definitions.get(label) else {
+            return false;
+        };
+
+        let mut depth = 0usize;
+        let mut blocks = Vec::new();
+
+        for event in &self.cursor.events[range.clone()] {
+            match event {
+                Event::Start(container, _) => {
+                    if depth == 0 {
+                        blocks.push(container);
+                    }
+
+                    depth += 1;
+                }
+                Event::End(..) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        !matches!(blocks[..], [] | [Container::Paragraph])
+    }
+
+    /// Render the list of endnotes, if there are any.
+    fn endnotes(&mut self) {
+        let sidenotes = self.options.footnotes == Footnotes::Sidenotes;
+        let mut open = false;
+
+        // NOTE: Footnotes may reference other footnotes, adding to the list as
+        //       we go.
+        let mut index = 0;
+
+        while let Some(label) = self.referenced.get(index).copied() {
+            let number = index + 1;
+
+            index += 1;
+
+            if !self.is_endnote(label) {
+                continue;
+            }
+
+            if !mem::replace(&mut open, true) {
+                self.out
+                    .push_str(r#"<section role="doc-endnotes" class="footnotes">"#);
+                self.out.push_str("\n<hr />\n<ol>\n");
+            }
+
+            let backlink = format!(r##"<a href="#fnref{number}" role="doc-backlink">↩︎</a>"##);
+
+            // NOTE: Alongside sidenotes, only some footnotes are listed, so
+            //       their number needs to be given explicitly.
+            match sidenotes {
+                true => self
+                    .out
+                    .push_str(&format!(r#"<li id="fn{number}" value="{number}">"#)),
+                false => self.out.push_str(&format!(r#"<li id="fn{number}">"#)),
+            }
+
+            self.out.push('\n');
+
+            if let Some(range) = self.definitions.get(label).cloned() {
+                self.tight.push(false);
+                self.run_range(range);
+                self.tight.pop();
+            }
+
+            self.push_within_paragraph(&backlink);
+
+            self.out.push_str("</li>\n");
+        }
+
+        if open {
+            self.out.push_str("</ol>\n</section>\n");
+        }
+    }
+
+    /// Render every remaining event.
+    fn run(&mut self) {
+        while let Some(event) = self.cursor.next() {
+            self.event(event);
+        }
+    }
+
+    fn event(&mut self, event: &'e Event<'static>) {
+        match event {
+            Event::Start(container, attributes) => self.start(container, attributes),
+            Event::End(container) => self.end(container),
+            Event::Str(s) => util::escape(s, &mut self.out),
+            Event::FootnoteReference(label) => self.footnote_reference(label),
+            Event::Symbol(s) => {
+                self.out.push(':');
+                util::escape(s, &mut self.out);
+                self.out.push(':');
+            }
+            Event::Hardbreak => self.out.push_str("<br />\n"),
+            Event::ThematicBreak(attributes) => {
+                self.out.push_str("<hr");
+                self.attributes(attributes, None);
+                self.out.push_str(" />\n");
+            }
+            Event::Escape | Event::Blankline | Event::Attributes(..) => {}
+            event => {
+                if let Some(s) = plain_text(event) {
+                    self.out.push_str(s);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, container: &'e Container<'static>, attributes: &'e Attributes<'static>) {
+        // NOTE: The start event has already been consumed.
+        let position = self.cursor.position - 1;
+
+        match container {
+            Container::Blockquote => {
+                self.tight.push(false);
+                self.open("blockquote", attributes, None);
+            }
+            Container::List { kind, tight } => {
+                self.tight.push(*tight);
+
+                match kind {
+                    ListKind::Ordered {
+                        numbering, start, ..
+                    } => {
+                        self.out.push_str("<ol");
+
+                        if *start != 1 {
+                            self.out.push_str(&format!(r#" start="{start}""#));
+                        }
+
+                        match numbering {
+                            OrderedListNumbering::Decimal => {}
+                            OrderedListNumbering::AlphaLower => self.out.push_str(r#" type="a""#),
+                            OrderedListNumbering::AlphaUpper => self.out.push_str(r#" type="A""#),
+                            OrderedListNumbering::RomanLower => self.out.push_str(r#" type="i""#),
+                            OrderedListNumbering::RomanUpper => self.out.push_str(r#" type="I""#),
+                        }
+
+                        self.attributes(attributes, None);
+                        self.out.push_str(">\n");
+                    }
+                    ListKind::Task(..) => self.open("ul", attributes, Some("task-list")),
+                    _ => self.open("ul", attributes, None),
+                }
+            }
+            Container::ListItem => self.open("li", attributes, None),
+            Container::TaskListItem { checked } => {
+                self.open("li", attributes, None);
+
+                self.out.push_str(match checked {
+                    true => r#"<input disabled="" type="checkbox" checked="" />"#,
+                    false => r#"<input disabled="" type="checkbox" />"#,
+                });
+            }
+            Container::DescriptionList => self.open("dl", attributes, None),
+            Container::DescriptionTerm => self.open("dt", attributes, None),
+            Container::DescriptionDetails => {
+                self.tight.push(false);
+                self.open("dd", attributes, None);
+            }
+            // NOTE: Footnotes are rendered where they are referenced, or at
+            //       the end of the page.
+            Container::Footnote { .. } | Container::LinkDefinition { .. } => {
+                self.cursor.skip_container()
+            }
+            Container::Table => {
+                self.tables.push(self.options.labels.at(position));
+                self.open("table", attributes, None);
+            }
+            Container::TableRow { .. } => self.open("tr", attributes, None),
+            Container::TableCell { alignment, head } => {
+                self.out.push_str(match head {
+                    true => "<th",
+                    false => "<td",
+                });
+
+                match alignment {
+                    Alignment::Unspecified => {}
+                    Alignment::Left => self.out.push_str(r#" style="text-align: left;""#),
+                    Alignment::Center => self.out.push_str(r#" style="text-align: center;""#),
+                    Alignment::Right => self.out.push_str(r#" style="text-align: right;""#),
+                }
+
+                self.attributes(attributes, None);
+                self.out.push('>');
+            }
+            Container::Caption => {
+                self.open("caption", attributes, None);
+
+                if let Some(Some(label)) = self.tables.last().copied() {
+                    self.out.push_str(&self::label(label, true));
+                }
+            }
+            Container::Section { id } => {
+                self.tight.push(false);
+                self.out.push_str("<section");
+
+                // NOTE: The id of a section is given to its heading instead,
+                //       so that it may label the section.
+                if !id.is_empty() {
+                    self.out.push_str(r#" aria-labelledby=""#);
+                    util::escape(id, &mut self.out);
+                    self.out.push('"');
+                }
+
+                self.attributes(attributes, None);
+                self.out.push_str(">\n");
+            }
+            Container::Div { class } => {
+                self.tight.push(false);
+
+                match self.options.labels.at(position) {
+                    Some(label) if label.kind.is_theorem() => {
+                        self.theorem(class, Some(label), attributes);
+                        return;
+                    }
+                    Some(label) => {
+                        self.figure(position, label, attributes);
+                        return;
+                    }
+                    None if *class == "proof" => {
+                        self.theorem(class, None, attributes);
+                        return;
+                    }
+                    None => {}
+                }
+
+                match self.options.site.callout(class) {
+                    Some(callout) => self.callout(class, &callout, attributes),
+                    None => {
+                        self.divs.push("</div>\n".into());
+                        self.open(
+                            "div",
+                            attributes,
+                            Some(*class).filter(|class| !class.is_empty()),
+                        );
+                    }
+                }
+            }
+            Container::Paragraph => {
+                if let Some(label) = self.captions.remove(&position) {
+                    self.caption = true;
+                    self.open("figcaption", attributes, None);
+                    self.out.push_str(&self::label(label, true));
+                } else if let Some(label) = self.options.labels.at(position) {
+                    self.image_figure(label, attributes);
+                } else if self.tight.last() != Some(&true) {
+                    self.open("p", attributes, None);
+                }
+            }
+            Container::Heading { level, id, .. } => {
+                self.out.push_str(&format!("<h{level}"));
+                self.id(id);
+
+                self.attributes(attributes, None);
+                self.out.push('>');
+            }
+            Container::RawBlock { .. } | Container::CodeBlock { .. }
+                if self.options.diagrams.contains_key(&position) =>
+            {
+                self.diagram(position, attributes);
+            }
+            Container::RawBlock { format } | Container::RawInline { format } => {
+                if *format == "html" {
+                    for event in &mut self.cursor {
+                        match event {
+                            Event::Str(s) => self.out.push_str(s),
+                            Event::End(..) => break,
+                            _ => {}
+                        }
+                    }
+                } else {
+                    self.cursor.skip_container();
+                }
+            }
+            Container::CodeBlock { language } => {
+                self.out.push_str("<pre");
+                self.attributes(attributes, None);
+                self.out.push_str("><code");
+
+                if !language.is_empty() {
+                    self.out.push_str(r#" class="language-"#);
+                    util::escape(language, &mut self.out);
+                    self.out.push('"');
+                }
+
+                self.out.push('>');
+            }
+            Container::Span => self.open("span", attributes, None),
+            Container::Link(url, link_type) => {
+                let label = numbering::reference(self.cursor.events, position)
+                    .and_then(|id| self.options.labels.get(id));
+
+                if let Some(label) = label {
+                    self.out.push_str(r#"<a href=""#);
+                    util::escape(url, &mut self.out);
+                    self.out.push('"');
+                    self.attributes(attributes, Some("reference"));
+                    self.out.push('>');
+                    util::escape(&label.reference(), &mut self.out);
+                    self.out.push_str("</a>");
+
+                    // NOTE: Skip the end of the link, as we've closed it already.
+                    self.cursor.position += 1;
+                    return;
+                }
+
+                self.out.push_str("<a");
+
+                if !url.is_empty() {
+                    self.out.push_str(r#" href=""#);
+
+                    if let LinkType::Email = link_type {
+                        self.out.push_str("mailto:");
+                    }
+
+                    util::escape(url, &mut self.out);
+                    self.out.push('"');
+                }
+
+                self.attributes(attributes, None);
+                self.out.push('>');
+            }
+            Container::Image(src, ..) => {
+                self.image(src, attributes);
+            }
+            Container::Verbatim => self.open("code", attributes, None),
+            Container::Math { display } => self.math(position, *display, attributes),
+            Container::Subscript => self.open("sub", attributes, None),
+            Container::Superscript => self.open("sup", attributes, None),
+            Container::Insert => self.open("ins", attributes, None),
+            Container::Delete => self.open("del", attributes, None),
+            Container::Strong => self.open("strong", attributes, None),
+            Container::Emphasis => self.open("em", attributes, None),
+            Container::Mark => self.open("mark", attributes, None),
+        }
+    }
+
+    fn end(&mut self, container: &'e Container<'static>) {
+        match container {
+            Container::Blockquote => {
+                self.tight.pop();
+                self.out.push_str("</blockquote>\n");
+            }
+            Container::List { kind, .. } => {
+                self.tight.pop();
+
+                self.out.push_str(match kind {
+                    ListKind::Ordered { .. } => "</ol>\n",
+                    _ => "</ul>\n",
+                });
+            }
+            Container::ListItem | Container::TaskListItem { .. } => self.out.push_str("</li>\n"),
+            Container::DescriptionList => self.out.push_str("</dl>\n"),
+            Container::DescriptionTerm => self.out.push_str("</dt>\n"),
+            Container::DescriptionDetails => {
+                self.tight.pop();
+                self.out.push_str("</dd>\n");
+            }
+            Container::Footnote { .. } | Container::LinkDefinition { .. } => {}
+            Container::Table => {
+                self.tables.pop();
+                self.out.push_str("</table>\n");
+            }
+            Container::TableRow { .. } => self.out.push_str("</tr>\n"),
+            Container::TableCell { head: true, .. } => self.out.push_str("</th>\n"),
+            Container::TableCell { head: false, .. } => self.out.push_str("</td>\n"),
+            Container::Caption => self.out.push_str("</caption>\n"),
+            Container::Section { .. } => {
+                self.tight.pop();
+                self.out.push_str("</section>\n");
+            }
+            Container::Div { class } => {
+                self.tight.pop();
+                let close = self.divs.pop().unwrap_or("</div>\n".into());
+
+                if *class == "proof" {
+                    self.push_within_paragraph(
+                        r#"<span class="qed" role="img" aria-label="End of proof">∎</span>"#,
+                    );
+                }
+
+                self.out.push_str(&close);
+            }
+            Container::Paragraph => {
+                if mem::take(&mut self.caption) {
+                    self.out.push_str("</figcaption>");
+                } else if self.tight.last() != Some(&true) {
+                    self.out.push_str("</p>");
+                }
+
+                self.out.push('\n');
+            }
+            Container::Heading { level, id, .. } => {
+                if !id.is_empty() {
+                    self.out.push_str(r##" <a class="anchor" href="#"##);
+                    util::escape(id, &mut self.out);
+                    self.out.push_str(r#"" aria-label="Permalink">#</a>"#);
+                }
+
+                self.out.push_str(&format!("</h{level}>\n"));
+            }
+            Container::RawBlock { .. } | Container::RawInline { .. } => {}
+            Container::CodeBlock { .. } => self.out.push_str("</code></pre>\n"),
+            Container::Span => self.out.push_str("</span>"),
+            Container::Link(..) => self.out.push_str("</a>"),
+            Container::Image(..) => {}
+            Container::Verbatim => self.out.push_str("</code>"),
+            // NOTE: Math is closed when it's opened, as its contents are
+            //       consumed all at once.
+            Container::Math { .. } => {}
+            Container::Subscript => self.out.push_str("</sub>"),
+            Container::Superscript => self.out.push_str("</sup>"),
+            Container::Insert => self.out.push_str("</ins>"),
+            Container::Delete => self.out.push_str("</del>"),
+            Container::Strong => self.out.push_str("</strong>"),
+            Container::Emphasis => self.out.push_str("</em>"),
+            Container::Mark => self.out.push_str("</mark>"),
+        }
+    }
+
+    /// Render the start of a callout, taking its title from the heading it
+    /// starts with, if any.
+    fn callout(&mut self, class: &str, callout: &Callout, attributes: &Attributes<'static>) {
+        while let Some(Event::Blankline) = self.cursor.peek() {
+            self.cursor.position += 1;
+        }
+
+        // NOTE: The heading keeps its id, as the table of contents still
+        //       links to it.
+        let mut id = "";
+
+        let title = match self.cursor.peek() {
+            Some(Event::Start(Container::Heading { id: heading, .. }, _)) => {
+                let out = mem::take(&mut self.out);
+
+                id = heading;
+
+                self.cursor.position += 1;
+
+                let mut depth = 0usize;
+
+                while let Some(event) = self.cursor.next() {
+                    match event {
+                        Event::Start(..) => depth += 1,
+                        Event::End(..) if depth == 0 => break,
+                        Event::End(..) => depth -= 1,
+                        _ => {}
+                    }
+
+                    self.event(event);
+                }
+
+                mem::replace(&mut self.out, out)
+            }
+            _ => util::escaped(&callout.title),
+        };
+
+        let mut heading = String::new();
+
+        if !callout.icon.is_empty() {
+            heading.push_str(r#"<span class="callout-icon" aria-hidden="true">"#);
+            util::escape(&callout.icon, &mut heading);
+            heading.push_str("</span> ");
+        }
+
+        heading.push_str(r#"<span class="callout-title""#);
+
+        if !id.is_empty() {
+            heading.push_str(r#" id=""#);
+            util::escape(id, &mut heading);
+            heading.push('"');
+        }
+
+        heading.push('>');
+        heading.push_str(title.trim());
+        heading.push_str("</span>");
+
+        let class = format!("callout {class}");
+
+        if callout.details {
+            self.open("details", attributes, Some(&class));
+            self.out.push_str("<summary>");
+            self.out.push_str(&heading);
+            self.out.push_str("</summary>\n");
+            self.divs.push("</details>\n".into());
+        } else {
+            self.open("aside", attributes, Some(&class));
+            self.out.push_str(r#"<p class="callout-heading">"#);
+            self.out.push_str(&heading);
+            self.out.push_str("</p>\n");
+            self.divs.push("</aside>\n".into());
+        }
+    }
+
+    /// Push `s` within the paragraph that was just closed, or on its own if
+    /// the output doesn't end with a paragraph.
+    fn push_within_paragraph(&mut self, s: &str) {
+        match self.out.strip_suffix("</p>\n") {
+            Some(rest) => {
+                self.out.truncate(rest.len());
+                self.out.push_str(s);
+                self.out.push_str("</p>\n");
+            }
+            None => {
+                self.out.push_str(s);
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Render the start of a theorem, lemma, definition or proof div, with
+    /// its title taken from its `title` attribute.
+    ///
+    /// Proofs aren't numbered, and instead end with a QED marker.
+    fn theorem(&mut self, class: &str, label: Option<Label>, attributes: &Attributes<'static>) {
+        let mut attributes = attributes.clone();
+        let mut title = None;
+
+        attributes.retain(|(kind, value)| match kind {
+            AttributeKind::Pair { key: "title" } => {
+                title = Some(value.parts().collect::<String>());
+                false
+            }
+            _ => true,
+        });
+
+        self.open("div", &attributes, Some(&format!("environment {class}")));
+        self.out.push_str(r#"<p class="environment-heading">"#);
+
+        match label {
+            Some(label) => self.out.push_str(&self::label(label, false)),
+            None => self.out.push_str(r#"<span class="label">Proof</span>"#),
+        }
+
+        if let Some(title) = title {
+            self.out.push_str(r#" <span class="environment-title">("#);
+            util::escape(&title, &mut self.out);
+            self.out.push_str(")</span>");
+        }
+
+        self.out.push_str(".</p>\n");
+        self.divs.push("</div>\n".into());
+    }
+
+    /// Render the start of a figure div, with the paragraph it ends with as
+    /// its caption, unless that's all there is to it.
+    fn figure(&mut self, position: usize, label: Label, attributes: &Attributes<'static>) {
+        let end = djot::end(self.cursor.events, position);
+        let mut depth = 0usize;
+        let mut blocks = 0usize;
+        let mut caption = None;
+
+        for (offset, event) in self.cursor.events[position + 1..end].iter().enumerate() {
+            match event {
+                Event::Start(container, _) => {
+                    if depth == 0 {
+                        blocks += 1;
+                        caption = matches!(container, Container::Paragraph)
+                            .then_some(position + 1 + offset);
+                    }
+
+                    depth += 1;
+                }
+                Event::End(..) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        self.open("figure", attributes, Some(label.kind.class()));
+
+        match caption.filter(|_| blocks > 1) {
+            Some(caption) => {
+                self.captions.insert(caption, label);
+                self.divs.push("</figure>\n".into());
+            }
+            None => {
+                let label = self::label(label, false);
+
+                self.divs
+                    .push(format!("<figcaption>{label}</figcaption>\n</figure>\n").into());
+            }
+        }
+    }
+
+    /// Render the diagram starting at `position` as a figure, consuming it.
+    fn diagram(&mut self, position: usize, attributes: &Attributes<'static>) {
+        self.cursor.skip_container();
+        self.open("figure", attributes, Some("diagram"));
+
+        if let Some(svg) = self.options.diagrams.get(&position) {
+            self.out.push_str(svg);
+        }
+
+        self.out.push_str("\n</figure>\n");
+    }
+
+    /// Render the math starting at `position`, consuming it.
+    ///
+    /// Math rendered to SVG is inlined, along with any MathML for screen
+    /// readers, and MathML on its own is inlined as is. Otherwise its source
+    /// is left within delimiters. Numbered equations are followed by their
+    /// number.
+    fn math(&mut self, position: usize, display: bool, attributes: &Attributes<'static>) {
+        let class = match display {
+            true => "math display",
+            false => "math inline",
+        };
+
+        self.open("span", attributes, Some(class));
+
+        let source = self.cursor.text_content();
+
+        let rendered = self
+            .options
+            .math
+            .get(&position)
+            .copied()
+            .unwrap_or_default();
+
+        match rendered {
+            RenderedMath {
+                svg: Some(svg),
+                mathml,
+            } => {
+                self.out
+                    .push_str(&svg.replacen("<svg ", r#"<svg aria-hidden="true" "#, 1));
+
+                if let Some(mathml) = mathml {
+                    self.out.push_str(r#"<span class="math-alternative">"#);
+                    self.out.push_str(mathml);
+                    self.out.push_str("</span>");
+                }
+            }
+            RenderedMath {
+                svg: None,
+                mathml: Some(mathml),
+            } => self.out.push_str(mathml),
+            RenderedMath {
+                svg: None,
+                mathml: None,
+            } => {
+                let (open, close) = match display {
+                    true => (r"\[", r"\]"),
+                    false => (r"\(", r"\)"),
+                };
+
+                self.out.push_str(open);
+                util::escape(&source, &mut self.out);
+                self.out.push_str(close);
+            }
+        }
+
+        let label = self
+            .options
+            .labels
+            .at(position)
+            .filter(|label| label.kind == Kind::Equation);
+
+        if let Some(label) = label {
+            self.out.push_str(r#"<span class="equation-number">"#);
+            self.out.push_str(&label.reference());
+            self.out.push_str("</span>");
+        }
+
+        self.out.push_str("</span>");
+    }
+
+    /// Render a paragraph containing nothing but an image as a figure, with
+    /// the alt text of the image as its caption.
+    fn image_figure(&mut self, label: Label, attributes: &Attributes<'static>) {
+        self.open("figure", attributes, Some(label.kind.class()));
+
+        if let Some(Event::Start(Container::Image(src, ..), attributes)) = self.cursor.next() {
+            let alt = self.image(src, attributes);
+
+            self.out.push_str("\n<figcaption>");
+            self.out.push_str(&self::label(label, !alt.is_empty()));
+            util::escape(&alt, &mut self.out);
+            self.out.push_str("</figcaption>\n");
+        }
+
+        // NOTE: Skip the end of the paragraph.
+        self.cursor.next();
+
+        self.out.push_str("</figure>\n");
+    }
+
+    /// Render an image, returning its alt text.
+    fn image(&mut self, src: &str, attributes: &Attributes<'static>) -> String {
+        let alt = self.cursor.text_content();
+
+        self.out.push_str(r#"<img alt=""#);
+        util::escape(&alt, &mut self.out);
+        self.out.push_str(r#"" src=""#);
+        util::escape(src, &mut self.out);
+        self.out.push('"');
+        self.attributes(attributes, None);
+        self.out.push_str(" />");
+
+        alt
+    }
+
+    /// Write an opening tag.
+    fn open(&mut self, tag: &str, attributes: &Attributes<'static>, class: Option<&str>) {
+        self.out.push('<');
+        self.out.push_str(tag);
+        self.attributes(attributes, class);
+        self.out.push('>');
+
+        if is_block(tag) {
+            self.out.push('\n');
+        }
+    }
+
+    /// Write an `id` attribute, if `id` isn't empty.
+    fn id(&mut self, id: &str) {
+        if !id.is_empty() {
+            self.out.push_str(r#" id=""#);
+            util::escape(id, &mut self.out);
+            self.out.push('"');
+        }
+    }
+
+    /// Write a list of attributes, merging `class` into the classes specified.
+    fn attributes(&mut self, attributes: &Attributes<'static>, class: Option<&str>) {
+        let mut classes: Vec<String> = class.into_iter().map(String::from).collect();
+
+        for (kind, value) in attributes.iter() {
+            let value = value.parts().collect::<String>();
+
+            match kind {
+                AttributeKind::Class => classes.push(value),
+                AttributeKind::Id => self.id(&value),
+                AttributeKind::Pair { key } => {
+                    self.out.push(' ');
+                    util::escape(key, &mut self.out);
+                    self.out.push_str(r#"=""#);
+                    util::escape(&value, &mut self.out);
+                    self.out.push('"');
+                }
+                AttributeKind::Comment => {}
+            }
+        }
+
+        if !classes.is_empty() {
+            self.out.push_str(r#" class=""#);
+            util::escape(&classes.join(" "), &mut self.out);
+            self.out.push('"');
+        }
+    }
+}
+
+/// Render the label of something numbered, such as `Figure 1`, followed by a
+/// colon if it precedes a caption.
+fn label(label: Label, caption: bool) -> String {
+    let Label { kind, number } = label;
+
+    match caption {
+        true => format!(r#"<span class="label">{} {number}:</span> "#, kind.name()),
+        false => format!(r#"<span class="label">{} {number}</span>"#, kind.name()),
+    }
+}
+
+/// Returns whether an element is a block element, which gets a trailing newline
+/// after its opening tag.
+fn is_block(tag: &str) -> bool {
+    matches!(
+        tag,
+        "blockquote"
+            | "ol"
+            | "ul"
+            | "dl"
+            | "dd"
+            | "table"
+            | "tr"
+            | "div"
+            | "section"
+            | "aside"
+            | "details"
+            | "figure"
+    )
+}
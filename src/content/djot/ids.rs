@@ -0,0 +1,147 @@
+//! Making sure every id within a page is unique.
+//!
+//! Djot generates ids for every section from its heading, but nothing stops
+//! those from colliding with one another, or with ids given explicitly with
+//! `{#id}`. Explicit ids take precedence, and generated ids are given a numeric
+//! suffix until they're unique.
+
+use std::{collections::HashSet, path::Path};
+
+use bevy::log::warn;
+use jotdown::{AttributeKind, AttributeValue, Attributes, Container, Event};
+
+/// The ids claimed so far within a page.
+#[derive(Debug, Default)]
+struct Ids {
+    /// Every id given explicitly within the page.
+    explicit: HashSet<String>,
+    /// Every id that has been claimed.
+    used: HashSet<String>,
+}
+
+impl Ids {
+    /// Claim `id`, returning the unique id to use in its place.
+    fn claim(&mut self, id: &str, explicit: bool) -> String {
+        let taken =
+            |ids: &Ids, id: &str| ids.used.contains(id) || (!explicit && ids.explicit.contains(id));
+
+        let mut unique = id.to_owned();
+        let mut suffix = 1;
+
+        while taken(self, &unique) {
+            suffix += 1;
+            unique = format!("{id}-{suffix}");
+        }
+
+        self.used.insert(unique.clone());
+
+        unique
+    }
+}
+
+/// Returns the explicit ids within a list of attributes.
+fn explicit_ids<'a>(attributes: &'a Attributes<'static>) -> impl Iterator<Item = String> + 'a {
+    attributes
+        .iter()
+        .filter(|(kind, _)| matches!(kind, AttributeKind::Id))
+        .map(|(_, value)| value.parts().collect())
+}
+
+/// Returns the first explicit id within a list of attributes.
+#[must_use]
+pub fn explicit_id(attributes: &Attributes<'static>) -> Option<String> {
+    explicit_ids(attributes).next()
+}
+
+/// Make every id within `events` unique, warning about duplicated explicit ids.
+///
+/// Afterwards, the id of each section is also the id of its heading, and
+/// headings no longer carry explicit id attributes of their own.
+pub fn dedupe(events: &mut [Event<'static>], path: &Path) {
+    let mut ids = Ids::default();
+
+    for event in events.iter() {
+        if let Event::Start(_, attributes) | Event::ThematicBreak(attributes) = event {
+            for id in explicit_ids(attributes) {
+                if ids.explicit.contains(&id) {
+                    warn!(
+                        "the id {id:?} is used more than once in {:?}",
+                        path.display()
+                    );
+                }
+
+                ids.explicit.insert(id);
+            }
+        }
+    }
+
+    let mut sections: Vec<String> = Vec::new();
+    let mut heading: Option<String> = None;
+
+    for index in 0..events.len() {
+        let (event, rest) = events[index..].split_at_mut(1);
+
+        match &mut event[0] {
+            Event::Start(Container::Section { id }, _) => {
+                // NOTE: A section takes the explicit id of its heading, if it has one.
+                let explicit_id = rest
+                    .iter()
+                    .find_map(|event| match event {
+                        Event::Start(Container::Heading { .. }, attributes) => {
+                            Some(explicit_id(attributes))
+                        }
+                        _ => None,
+                    })
+                    .flatten();
+
+                let unique = match explicit_id {
+                    Some(explicit_id) => ids.claim(&explicit_id, true),
+                    None => ids.claim(id, false),
+                };
+
+                *id = unique.clone().into();
+                sections.push(unique);
+            }
+            Event::End(Container::Section { id }) => {
+                if let Some(unique) = sections.pop() {
+                    *id = unique.into();
+                }
+            }
+            Event::Start(
+                Container::Heading {
+                    has_section, id, ..
+                },
+                attributes,
+            ) => {
+                let explicit_id = self::explicit_id(attributes);
+
+                attributes.retain(|(kind, _)| !matches!(kind, AttributeKind::Id));
+
+                let unique = match (*has_section, explicit_id) {
+                    (true, _) => sections.last().cloned().unwrap_or_default(),
+                    (false, Some(explicit_id)) => ids.claim(&explicit_id, true),
+                    (false, None) if id.is_empty() => String::new(),
+                    (false, None) => ids.claim(id, false),
+                };
+
+                *id = unique.clone().into();
+                heading = Some(unique);
+            }
+            Event::End(Container::Heading { id, .. }) => {
+                if let Some(unique) = heading.take() {
+                    *id = unique.into();
+                }
+            }
+            Event::Start(_, attributes) | Event::ThematicBreak(attributes) => {
+                for (kind, value) in attributes.iter_mut() {
+                    if matches!(kind, AttributeKind::Id) {
+                        let id: String = value.parts().collect();
+
+                        *value = AttributeValue::from(ids.claim(&id, true));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,699 @@
+//! Transpiling djot events to typst markup, so that pages may be printed.
+//!
+//! Inline formatting is written as function calls, such as `#emph[...]`,
+//! rather than typst's own shorthands, as those depend on word boundaries in
+//! ways djot's syntax doesn't. Calls are ended with a `;`, so that text
+//! following them is never taken as a continuation of the call.
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    ops::Range,
+};
+
+use jotdown::{
+    AttributeKind, Attributes, Container, Event, LinkType, ListKind, OrderedListNumbering,
+};
+
+use crate::{
+    content::{
+        diagram,
+        djot::{self, Cursor, ids},
+        numbering::{self, Kind, Label, Labels},
+    },
+    site::Site,
+};
+
+/// Options for transpiling djot.
+#[derive(Debug, Clone, Copy)]
+pub struct Options<'a> {
+    /// The site, which configures callouts.
+    pub site: &'a Site,
+    /// The labels of everything numbered.
+    pub labels: &'a Labels,
+    /// The typst of math spans written in LaTeX, keyed by the position of the
+    /// event that starts them.
+    pub math: &'a HashMap<usize, &'a str>,
+}
+
+/// Transpile a list of djot events to typst markup.
+#[must_use]
+pub fn transpile(events: &[Event<'static>], options: Options) -> String {
+    let mut transpiler = Transpiler::new(events, options);
+
+    transpiler.run();
+    transpiler.out
+}
+
+/// Escape `input` for use within typst markup, appending it to `output`.
+fn escape(input: &str, output: &mut String) {
+    for c in input.chars() {
+        if matches!(
+            c,
+            '\\' | '#'
+                | '*'
+                | '_'
+                | '`'
+                | '$'
+                | '<'
+                | '>'
+                | '@'
+                | '['
+                | ']'
+                | '~'
+                | '='
+                | '-'
+                | '+'
+                | '/'
+        ) {
+            output.push('\\');
+        }
+
+        output.push(c);
+    }
+}
+
+/// Returns `input` as a typst string literal.
+#[must_use]
+pub fn string(input: &str) -> String {
+    let mut output = String::with_capacity(input.len() + 2);
+
+    output.push('"');
+
+    for c in input.chars() {
+        match c {
+            '"' => output.push_str(r#"\""#),
+            '\\' => output.push_str(r"\\"),
+            '\n' => output.push_str(r"\n"),
+            '\r' => output.push_str(r"\r"),
+            '\t' => output.push_str(r"\t"),
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+/// Returns the ranges of the containers directly within `range`, including
+/// their start and end events.
+fn children(events: &[Event<'static>], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut children = Vec::new();
+    let mut position = range.start;
+
+    while position < range.end {
+        match &events[position] {
+            Event::Start(..) => {
+                let end = djot::end(events, position);
+
+                children.push(position..end + 1);
+                position = end + 1;
+            }
+            _ => position += 1,
+        }
+    }
+
+    children
+}
+
+/// Returns the value of the `key` attribute, if there is one.
+fn attribute(attributes: &Attributes<'static>, key: &str) -> Option<String> {
+    attributes.iter().find_map(|(kind, value)| match kind {
+        AttributeKind::Pair { key: other } if *other == key => Some(value.parts().collect()),
+        _ => None,
+    })
+}
+
+/// A transpiler that walks over a list of djot events.
+#[derive(Debug)]
+struct Transpiler<'e> {
+    /// Where we are within the events.
+    cursor: Cursor<'e>,
+    /// How the events are transpiled.
+    options: Options<'e>,
+    /// The closing markup of the divs and tables currently open.
+    closing: Vec<String>,
+    /// The range of events within each footnote, keyed by its label.
+    definitions: HashMap<&'e str, Range<usize>>,
+    /// The labels of the footnotes transpiled so far, in order.
+    footnotes: Vec<&'e str>,
+    /// The ids that are given a label, which are all that may be linked to.
+    labelled: HashSet<&'e str>,
+    /// The transpiled output.
+    out: String,
+}
+
+impl<'e> Transpiler<'e> {
+    fn new(events: &'e [Event<'static>], options: Options<'e>) -> Transpiler<'e> {
+        let mut definitions = HashMap::new();
+
+        // NOTE: Headings are labelled with their id, and everything numbered
+        //       with its explicit id, if it has one.
+        let mut labelled: HashSet<&'e str> =
+            options.labels.ids.keys().map(String::as_str).collect();
+
+        for (start, event) in events.iter().enumerate() {
+            match event {
+                Event::Start(Container::Footnote { label }, _) => {
+                    definitions.insert(*label, start + 1..djot::end(events, start));
+                }
+                Event::Start(Container::Heading { id, .. }, _) if !id.is_empty() => {
+                    labelled.insert(id);
+                }
+                _ => {}
+            }
+        }
+
+        Transpiler {
+            cursor: Cursor::new(events),
+            options,
+            closing: Vec::new(),
+            definitions,
+            footnotes: Vec::new(),
+            labelled,
+            out: String::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(event) = self.cursor.next() {
+            self.event(event);
+        }
+    }
+
+    /// Transpile the events within `range` on their own, returning the
+    /// markup rather than adding it to the output.
+    fn content(&mut self, range: Range<usize>) -> String {
+        let out = mem::take(&mut self.out);
+        let position = mem::replace(&mut self.cursor.position, range.start);
+        let end = mem::replace(&mut self.cursor.end, range.end);
+
+        self.run();
+
+        self.cursor.position = position;
+        self.cursor.end = end;
+
+        mem::replace(&mut self.out, out).trim().to_owned()
+    }
+
+    /// Start a new block, making sure it's on a line of its own.
+    fn block(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// Add a label, if there's an id to label with.
+    fn label(&mut self, id: Option<String>) {
+        if let Some(id) = id.filter(|id| !id.is_empty()) {
+            self.out.push_str(&format!(" <{id}>"));
+        }
+    }
+
+    fn event(&mut self, event: &'e Event<'static>) {
+        match event {
+            Event::Start(container, attributes) => self.start(container, attributes),
+            Event::End(container) => self.end(container),
+            Event::Str(s) | Event::Symbol(s) => escape(s, &mut self.out),
+            // NOTE: Footnotes are transpiled where they're first referenced,
+            //       and referred to by their label afterwards. This also keeps
+            //       footnotes that reference themselves from doing so forever.
+            Event::FootnoteReference(label) => {
+                if let Some(index) = self.footnotes.iter().position(|other| other == label) {
+                    self.out
+                        .push_str(&format!("#footnote(<footnote-{}>);", index + 1));
+                    return;
+                }
+
+                self.footnotes.push(label);
+
+                let number = self.footnotes.len();
+                let content = match self.definitions.get(label).cloned() {
+                    Some(range) => self.content(range),
+                    None => String::new(),
+                };
+
+                self.out
+                    .push_str(&format!("#footnote[{content}]<footnote-{number}>"));
+            }
+            Event::LeftSingleQuote => self.out.push('‘'),
+            Event::RightSingleQuote => self.out.push('’'),
+            Event::LeftDoubleQuote => self.out.push('“'),
+            Event::RightDoubleQuote => self.out.push('”'),
+            Event::Ellipsis => self.out.push('…'),
+            Event::EnDash => self.out.push('–'),
+            Event::EmDash => self.out.push('—'),
+            Event::NonBreakingSpace => self.out.push('~'),
+            // NOTE: A line break in typst is a space, and avoids text at the
+            //       start of a line being taken as markup.
+            Event::Softbreak => self.out.push(' '),
+            Event::Hardbreak => self.out.push_str(" \\\n"),
+            Event::ThematicBreak(_) => {
+                self.block();
+                self.out.push_str("#line(length: 100%)\n\n");
+            }
+            Event::Escape | Event::Blankline | Event::Attributes(_) => {}
+        }
+    }
+
+    fn start(&mut self, container: &'e Container<'static>, attributes: &'e Attributes<'static>) {
+        // NOTE: The start event has already been consumed.
+        let position = self.cursor.position - 1;
+
+        match container {
+            Container::Blockquote => {
+                self.block();
+                self.out.push_str("#quote(block: true)[");
+            }
+            Container::List { kind, tight } => {
+                self.block();
+
+                match kind {
+                    ListKind::Ordered {
+                        numbering, start, ..
+                    } => {
+                        let numbering = match numbering {
+                            OrderedListNumbering::Decimal => "1.",
+                            OrderedListNumbering::AlphaLower => "a.",
+                            OrderedListNumbering::AlphaUpper => "A.",
+                            OrderedListNumbering::RomanLower => "i.",
+                            OrderedListNumbering::RomanUpper => "I.",
+                        };
+
+                        self.out.push_str(&format!(
+                            r#"#enum(tight: {tight}, start: {start}, numbering: "{numbering}", "#
+                        ));
+                    }
+                    _ => self.out.push_str(&format!("#list(tight: {tight}, ")),
+                }
+            }
+            Container::ListItem => self.out.push('['),
+            Container::TaskListItem { checked } => self.out.push_str(match checked {
+                true => "[☒ ",
+                false => "[☐ ",
+            }),
+            Container::DescriptionList => {
+                self.block();
+                self.out.push_str("#terms(");
+            }
+            Container::DescriptionTerm => self.out.push_str("terms.item["),
+            Container::DescriptionDetails => self.out.push('['),
+            // NOTE: Footnotes are transpiled where they're referenced, and
+            //       captions along with their table.
+            Container::Footnote { .. } | Container::LinkDefinition { .. } | Container::Caption => {
+                self.cursor.skip_container();
+            }
+            Container::Table => self.table(position),
+            Container::TableRow { head: true } => self.out.push_str("table.header("),
+            Container::TableRow { head: false } => {}
+            Container::TableCell { .. } => self.out.push('['),
+            Container::Section { .. } => {}
+            Container::Div { class } => self.div(position, class, attributes),
+            Container::Paragraph => {
+                self.block();
+
+                if numbering::image_figure(self.cursor.events, position) {
+                    self.image_figure(position, attributes);
+                }
+            }
+            Container::Heading { level, .. } => {
+                self.block();
+                self.out.push('\n');
+                self.out.push_str(&"=".repeat(usize::from(*level)));
+                self.out.push(' ');
+            }
+            Container::RawBlock { format } | Container::RawInline { format } => {
+                let text = self.cursor.text_content();
+
+                if *format == "typst" {
+                    self.out.push_str(&text);
+                }
+            }
+            Container::CodeBlock { language } => {
+                let diagram = diagram::is_diagram(container, attributes);
+                let text = self.cursor.text_content();
+
+                self.block();
+
+                // NOTE: Diagrams are scoped, so that their rules don't apply
+                //       to the rest of the document.
+                if diagram {
+                    self.out.push_str("#[\n");
+                    self.out.push_str(&text);
+                    self.out.push_str("\n]");
+                } else if language.is_empty() {
+                    self.out
+                        .push_str(&format!("#raw(block: true, {})", string(&text)));
+                } else {
+                    self.out.push_str(&format!(
+                        "#raw(block: true, lang: {}, {})",
+                        string(language),
+                        string(&text)
+                    ));
+                }
+
+                self.out.push_str("\n\n");
+            }
+            Container::Span => {}
+            Container::Link(url, link_type) => {
+                let reference = numbering::reference(self.cursor.events, position)
+                    .filter(|id| self.options.labels.get(id).is_some());
+
+                if let Some(id) = reference {
+                    self.out.push_str(&format!("#ref(<{id}>);"));
+                    self.cursor.skip_container();
+                } else if let Some(id) = url.strip_prefix('#') {
+                    // NOTE: Links to anything that isn't labelled are kept as
+                    //       their text alone.
+                    match self.labelled.contains(id) {
+                        true => self.out.push_str(&format!("#link(<{id}>)[")),
+                        false => self.out.push_str("#["),
+                    }
+                } else if let LinkType::Email = link_type {
+                    self.out
+                        .push_str(&format!("#link({})[", string(&format!("mailto:{url}"))));
+                } else {
+                    self.out.push_str(&format!("#link({})[", string(url)));
+                }
+            }
+            Container::Image(src, _) => {
+                let alt = self.cursor.text_content();
+
+                self.image(src, &alt);
+            }
+            Container::Verbatim => {
+                let text = self.cursor.text_content();
+
+                self.out.push_str(&format!("#raw({});", string(&text)));
+            }
+            Container::Math { display } => {
+                let source = self.cursor.text_content();
+                let math = match self.options.math.get(&position) {
+                    Some(typst) => typst,
+                    None => source.as_str(),
+                };
+
+                match (display, ids::explicit_id(attributes)) {
+                    (false, _) => self.out.push_str(&format!("${math}$")),
+                    (true, Some(id)) => self.out.push_str(&format!("$ {math} $ <{id}>")),
+                    (true, None) => self.out.push_str(&format!(
+                        "#math.equation(block: true, numbering: none, $ {math} $);"
+                    )),
+                }
+            }
+            Container::Subscript => self.out.push_str("#sub["),
+            Container::Superscript => self.out.push_str("#super["),
+            Container::Insert => self.out.push_str("#underline["),
+            Container::Delete => self.out.push_str("#strike["),
+            Container::Strong => self.out.push_str("#strong["),
+            Container::Emphasis => self.out.push_str("#emph["),
+            Container::Mark => self.out.push_str("#highlight["),
+        }
+    }
+
+    fn end(&mut self, container: &'e Container<'static>) {
+        match container {
+            Container::Blockquote => self.out.push_str("]\n\n"),
+            Container::List { .. } | Container::DescriptionList => self.out.push_str(")\n\n"),
+            Container::ListItem
+            | Container::TaskListItem { .. }
+            | Container::DescriptionDetails
+            | Container::TableCell { .. } => {
+                let trimmed = self.out.trim_end().len();
+
+                self.out.truncate(trimmed);
+                self.out.push_str("], ");
+            }
+            Container::DescriptionTerm => self.out.push(']'),
+            Container::TableRow { head: true } => self.out.push_str("), "),
+            Container::Table | Container::Div { .. } => {
+                let closing = self.closing.pop().unwrap_or_default();
+
+                self.out.push_str(&closing);
+            }
+            Container::Paragraph => self.out.push_str("\n\n"),
+            Container::Heading { id, .. } => {
+                self.label(Some(id.to_string()));
+                self.out.push_str("\n\n");
+            }
+            Container::Link(..)
+            | Container::Subscript
+            | Container::Superscript
+            | Container::Insert
+            | Container::Delete
+            | Container::Strong
+            | Container::Emphasis
+            | Container::Mark => self.out.push_str("];"),
+            Container::TableRow { head: false }
+            | Container::Footnote { .. }
+            | Container::LinkDefinition { .. }
+            | Container::Caption
+            | Container::Section { .. }
+            | Container::RawBlock { .. }
+            | Container::RawInline { .. }
+            | Container::CodeBlock { .. }
+            | Container::Span
+            | Container::Image(..)
+            | Container::Verbatim
+            | Container::Math { .. } => {}
+        }
+    }
+
+    /// Transpile an image, or a link to it if it's remote and can't be
+    /// embedded.
+    fn image(&mut self, src: &str, alt: &str) {
+        if src.contains("://") {
+            let mut text = String::new();
+
+            escape(alt, &mut text);
+
+            self.out
+                .push_str(&format!("#link({})[{text}];", string(src)));
+        } else {
+            self.out.push_str(&format!(
+                "#box(image({}, alt: {}));",
+                string(src),
+                string(alt)
+            ));
+        }
+    }
+
+    /// Transpile a paragraph containing nothing but an image as a figure, with
+    /// the alt text of the image as its caption.
+    fn image_figure(&mut self, position: usize, attributes: &Attributes<'static>) {
+        let Some(Event::Start(Container::Image(src, _), image_attributes)) =
+            self.cursor.events.get(position + 1)
+        else {
+            return;
+        };
+
+        self.cursor.position = position + 2;
+
+        let alt = self.cursor.text_content();
+        let mut caption = String::new();
+
+        escape(&alt, &mut caption);
+
+        self.out.push_str(&format!(
+            "#figure(image({}, alt: {}), caption: [{caption}])",
+            string(src),
+            string(&alt)
+        ));
+        self.label(ids::explicit_id(attributes).or_else(|| ids::explicit_id(image_attributes)));
+
+        // NOTE: The end of the paragraph adds the trailing blank line.
+        self.cursor.position = djot::end(self.cursor.events, position);
+    }
+
+    /// Transpile the start of a table, as a figure if it has a caption.
+    fn table(&mut self, position: usize) {
+        let end = djot::end(self.cursor.events, position);
+        let rows = children(self.cursor.events, position + 1..end);
+
+        let caption = rows
+            .iter()
+            .find(|row| {
+                matches!(
+                    self.cursor.events[row.start],
+                    Event::Start(Container::Caption, _)
+                )
+            })
+            .cloned();
+
+        let columns = rows
+            .iter()
+            .find(|row| {
+                matches!(
+                    self.cursor.events[row.start],
+                    Event::Start(Container::TableRow { .. }, _)
+                )
+            })
+            .map_or(1, |row| {
+                children(self.cursor.events, row.start + 1..row.end - 1).len()
+            });
+
+        self.block();
+
+        match caption {
+            Some(caption) => {
+                let caption = self.content(caption.start + 1..caption.end - 1);
+                let id = match &self.cursor.events[position] {
+                    Event::Start(_, attributes) => ids::explicit_id(attributes),
+                    _ => None,
+                };
+
+                self.out.push_str(&format!(
+                    "#figure(kind: table, caption: [{caption}], table(columns: {columns}, "
+                ));
+
+                let mut closing = String::from("))");
+
+                if let Some(id) = id {
+                    closing.push_str(&format!(" <{id}>"));
+                }
+
+                closing.push_str("\n\n");
+                self.closing.push(closing);
+            }
+            None => {
+                self.out.push_str(&format!("#table(columns: {columns}, "));
+                self.closing.push(")\n\n".into());
+            }
+        }
+    }
+
+    /// Transpile the start of a div, which may be a figure, a theorem or a
+    /// callout.
+    fn div(&mut self, position: usize, class: &str, attributes: &Attributes<'static>) {
+        let end = djot::end(self.cursor.events, position);
+        let id = ids::explicit_id(attributes);
+
+        self.block();
+
+        match self.options.labels.at(position) {
+            Some(label) if label.kind.is_theorem() => {
+                self.theorem(label.kind.name(), Some(label), attributes);
+            }
+            Some(label) => self.figure(position, end, label, id),
+            None if class == "proof" => self.theorem("Proof", None, attributes),
+            None => match self.options.site.callout(class) {
+                Some(callout) => {
+                    let children = children(self.cursor.events, position + 1..end);
+
+                    // NOTE: A callout takes its title from the heading it
+                    //       starts with, if any.
+                    let title = match children.first() {
+                        Some(heading)
+                            if matches!(
+                                self.cursor.events[heading.start],
+                                Event::Start(Container::Heading { .. }, _)
+                            ) =>
+                        {
+                            let title = self.content(heading.start + 1..heading.end - 1);
+
+                            self.cursor.position = heading.end;
+                            title
+                        }
+                        _ => {
+                            let mut title = String::new();
+
+                            escape(&callout.title, &mut title);
+                            title
+                        }
+                    };
+
+                    self.out.push_str(&format!(
+                        "#block(inset: 8pt, stroke: (left: 2pt))[#strong[{title}] \\\n"
+                    ));
+                    self.closing.push("]\n\n".into());
+                }
+                None => self.closing.push(String::new()),
+            },
+        }
+    }
+
+    /// Transpile the start of a figure div, with the paragraph it ends with
+    /// as its caption, unless that's all there is to it.
+    fn figure(&mut self, position: usize, end: usize, label: Label, id: Option<String>) {
+        let children = children(self.cursor.events, position + 1..end);
+        let kind = match label.kind {
+            Kind::Listing => "raw",
+            Kind::Table => "table",
+            _ => "image",
+        };
+
+        let caption = children
+            .last()
+            .filter(|_| children.len() > 1)
+            .filter(|last| {
+                matches!(
+                    self.cursor.events[last.start],
+                    Event::Start(Container::Paragraph, _)
+                )
+            })
+            .cloned();
+
+        let body = match &caption {
+            Some(caption) => self.content(position + 1..caption.start),
+            None => self.content(position + 1..end),
+        };
+
+        self.out.push_str(&format!("#figure(kind: {kind}, "));
+
+        if let Some(caption) = caption {
+            let caption = self.content(caption.start + 1..caption.end - 1);
+
+            self.out.push_str(&format!("caption: [{caption}], "));
+        }
+
+        self.out.push_str(&format!("[\n{body}\n])"));
+        self.label(id);
+        self.out.push_str("\n\n");
+
+        // NOTE: The whole figure has been transpiled, so the end of the div
+        //       has nothing left to close.
+        self.cursor.position = end;
+        self.closing.push(String::new());
+    }
+
+    /// Transpile the start of a theorem, lemma, definition or proof div.
+    ///
+    /// Numbered environments are figures of their own kind, so that they may
+    /// be referenced, numbered as they are on the web.
+    fn theorem(&mut self, name: &str, label: Option<Label>, attributes: &Attributes<'static>) {
+        let mut heading = match label {
+            Some(label) => format!("{} {}", name, label.number),
+            None => name.to_owned(),
+        };
+
+        if let Some(title) = attribute(attributes, "title") {
+            heading.push_str(" (");
+            escape(&title, &mut heading);
+            heading.push(')');
+        }
+
+        let id = ids::explicit_id(attributes);
+
+        match label {
+            Some(label) => self.out.push_str(&format!(
+                "#figure(kind: {}, supplement: [{name}], numbering: (..) => \"{}\", \
+                 outlined: false, align(left)[#strong[{heading}.] ",
+                string(label.kind.class()),
+                label.number
+            )),
+            None => self.out.push_str(&format!("#block[#emph[{heading}.] ")),
+        }
+
+        let mut closing = match label {
+            Some(_) => String::from("])"),
+            None => String::from("#h(1fr) $square$]"),
+        };
+
+        if let Some(id) = id {
+            closing.push_str(&format!(" <{id}>"));
+        }
+
+        closing.push_str("\n\n");
+        self.closing.push(closing);
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Write as _},
+    io::{Cursor, Write as _},
+    path::Path,
+};
+
+use anyhow::Context as _;
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, Or, With, Without},
+    system::{Commands, Query, Res},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use url::Url;
+use zip::{CompressionMethod, ZipWriter, result::ZipResult, write::SimpleFileOptions};
+
+use crate::{
+    Now,
+    content::{
+        Output,
+        collection::{Collection, CollectionConfig},
+        feed,
+        page::{Body, Created, Description, Draft, Page, Title},
+        route::Route,
+        taxonomy::{self, Terms},
+        typ::TypstPage,
+    },
+    site::Site,
+    util::{self, escaped},
+};
+
+/// Marker component for generated EPUBs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Epub;
+
+/// The file name of EPUBs, within the route of what they bundle.
+pub const FILE: &str = "book.epub";
+
+/// The stylesheet embedded within every EPUB, compiled from the site styles.
+const STYLESHEET: &str = grass::include!("styles/epub.scss");
+
+/// A single chapter within an EPUB.
+#[derive(Debug, Clone)]
+struct Chapter<'a> {
+    title: &'a str,
+    url: Url,
+    created: Option<NaiveDate>,
+    body: &'a str,
+}
+
+impl Chapter<'_> {
+    /// The name of the document of the chapter at `index` within its book.
+    fn file(index: usize) -> String {
+        format!("chapter-{}.xhtml", index + 1)
+    }
+}
+
+/// A collection or series, and the chapters that are in it.
+#[derive(Debug, Clone)]
+struct Book<'a> {
+    title: String,
+    description: &'a str,
+    /// The route the EPUB is published under.
+    route: Route,
+    chapters: Vec<&'a Chapter<'a>>,
+}
+
+/// Bundle every collection and taxonomy term that opted in into an EPUB, with
+/// its pages as chapters from oldest to newest.
+pub fn generate(
+    pages: Query<
+        (
+            &Title,
+            &Route,
+            &Body,
+            &Terms,
+            Option<&Created>,
+            Option<&Collection>,
+        ),
+        // NOTE: Pages exported with typst's HTML export aren't guaranteed to
+        //       be valid XHTML, so typst pages are left out entirely.
+        (With<Page>, Without<Draft>, Without<TypstPage>),
+    >,
+    collections: Query<(Entity, &Title, &Description, &Route, &CollectionConfig)>,
+    changed: Query<
+        (),
+        (
+            With<Page>,
+            Or<(Changed<Body>, Changed<Terms>, Changed<Collection>)>,
+        ),
+    >,
+    epubs: Query<Entity, With<Epub>>,
+    site: Res<Site>,
+    now: Res<Now>,
+    mut commands: Commands,
+) -> Result<()> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    for epub in epubs {
+        commands.entity(epub).despawn();
+    }
+
+    let mut chapters: Vec<(Chapter, &Terms, Option<Entity>)> = pages
+        .iter()
+        .map(
+            |(Title(title), route, Body(body), terms, created, collection)| {
+                let chapter = Chapter {
+                    title,
+                    url: route.url(&site.url),
+                    created: created.map(|created| created.0),
+                    body,
+                };
+
+                (
+                    chapter,
+                    terms,
+                    collection.map(|Collection(collection)| *collection),
+                )
+            },
+        )
+        .collect();
+
+    // Oldest first, with any undated chapters last.
+    chapters.sort_by(|(lhs, ..), (rhs, ..)| match (lhs.created, rhs.created) {
+        (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+        (lhs, rhs) => rhs.cmp(&lhs),
+    });
+
+    let mut books = Vec::new();
+
+    for (collection, Title(title), Description(description), route, config) in &collections {
+        if !config.epub {
+            continue;
+        }
+
+        books.push(Book {
+            title: title.clone(),
+            description,
+            route: route.clone(),
+            chapters: chapters
+                .iter()
+                .filter(|(.., chapter_collection)| *chapter_collection == Some(collection))
+                .map(|(chapter, ..)| chapter)
+                .collect(),
+        });
+    }
+
+    for taxonomy in site.taxonomies.iter().filter(|taxonomy| taxonomy.epub) {
+        let mut terms = BTreeMap::<String, Book>::new();
+
+        for (chapter, Terms(chapter_terms), _) in &chapters {
+            // NOTE: Terms may be spelled differently, yet share a slug, such
+            //       as `Rust` and `rust`.
+            let mut slugs = HashSet::new();
+
+            for term in chapter_terms.get(&taxonomy.name).into_iter().flatten() {
+                let name = term.trim();
+                let slug = util::slugify(name);
+
+                if slug.is_empty() || !slugs.insert(slug.clone()) {
+                    continue;
+                }
+
+                terms
+                    .entry(slug)
+                    .or_insert_with(|| Book {
+                        title: name.into(),
+                        description: "",
+                        route: taxonomy::route(&taxonomy.name, name),
+                        chapters: Vec::new(),
+                    })
+                    .chapters
+                    .push(chapter);
+            }
+        }
+
+        books.extend(terms.into_values());
+    }
+
+    for book in books {
+        if book.chapters.is_empty() {
+            continue;
+        }
+
+        let epub = archive(&site, &book, now.0)
+            .with_context(|| format!("bundling {:?} into an EPUB", book.title))?;
+
+        let path = Path::new(book.route.0.trim_start_matches('/'));
+
+        commands.spawn((
+            Epub,
+            Output::<Path>(path.join(FILE).into()),
+            Output::<[u8]>(epub.into()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Bundle `book` into an EPUB container.
+fn archive(site: &Site, book: &Book, now: DateTime<Utc>) -> ZipResult<Vec<u8>> {
+    let mut package = String::new();
+    let mut nav = String::new();
+
+    // NOTE: Writing to a `String` never fails.
+    let _ = self::package(&mut package, site, book, now);
+    let _ = self::nav(&mut nav, site, book);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // NOTE: The mimetype must come first, and be stored uncompressed, so that
+    //       it can be read at a fixed offset.
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER.as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package.as_bytes())?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav.as_bytes())?;
+
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLESHEET.as_bytes())?;
+
+    for (index, chapter) in book.chapters.iter().enumerate() {
+        let mut document = String::new();
+        let _ = self::chapter(&mut document, site, book, chapter);
+
+        zip.start_file(format!("OEBPS/{}", Chapter::file(index)), deflated)?;
+        zip.write_all(document.as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// The container document, pointing readers at the package document.
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml" />
+</rootfiles>
+</container>
+"#;
+
+/// Render the package document, which describes the book and its contents.
+fn package(out: &mut String, site: &Site, book: &Book, now: DateTime<Utc>) -> fmt::Result {
+    let url = book.route.url(&site.url);
+
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id" xml:lang="{}">"#,
+        escaped(&site.language)
+    )?;
+    writeln!(
+        out,
+        r#"<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">"#
+    )?;
+    writeln!(
+        out,
+        r#"<dc:identifier id="id">{}</dc:identifier>"#,
+        escaped(url.as_str())
+    )?;
+    writeln!(out, "<dc:title>{}</dc:title>", escaped(&book.title))?;
+    writeln!(
+        out,
+        "<dc:language>{}</dc:language>",
+        escaped(&site.language)
+    )?;
+
+    if !site.author.name.is_empty() {
+        writeln!(
+            out,
+            "<dc:creator>{}</dc:creator>",
+            escaped(&site.author.name)
+        )?;
+    }
+
+    if !book.description.is_empty() {
+        writeln!(
+            out,
+            "<dc:description>{}</dc:description>",
+            escaped(book.description)
+        )?;
+    }
+
+    writeln!(out, "<dc:publisher>{}</dc:publisher>", escaped(&site.title))?;
+    writeln!(out, "<dc:source>{}</dc:source>", escaped(url.as_str()))?;
+    writeln!(
+        out,
+        r#"<meta property="dcterms:modified">{}</meta>"#,
+        now.format("%Y-%m-%dT%H:%M:%SZ")
+    )?;
+    writeln!(out, "</metadata>")?;
+
+    writeln!(out, "<manifest>")?;
+    writeln!(
+        out,
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav" />"#
+    )?;
+    writeln!(
+        out,
+        r#"<item id="style" href="style.css" media-type="text/css" />"#
+    )?;
+
+    for (index, chapter) in book.chapters.iter().enumerate() {
+        // NOTE: Documents with inline SVG or MathML, such as math, have to
+        // say so.
+        let properties: Vec<&str> = [("<svg", "svg"), ("<math", "mathml")]
+            .into_iter()
+            .filter(|(tag, _)| chapter.body.contains(tag))
+            .map(|(_, property)| property)
+            .collect();
+
+        let properties = match properties.is_empty() {
+            true => String::new(),
+            false => format!(r#" properties="{}""#, properties.join(" ")),
+        };
+
+        writeln!(
+            out,
+            r#"<item id="chapter-{}" href="{}" media-type="application/xhtml+xml"{properties} />"#,
+            index + 1,
+            Chapter::file(index)
+        )?;
+    }
+
+    writeln!(out, "</manifest>")?;
+
+    writeln!(out, "<spine>")?;
+
+    for index in 0..book.chapters.len() {
+        writeln!(out, r#"<itemref idref="chapter-{}" />"#, index + 1)?;
+    }
+
+    writeln!(out, "</spine>")?;
+    writeln!(out, "</package>")
+}
+
+/// Render the start of an XHTML document within the book.
+fn head(out: &mut String, site: &Site, title: &str) -> fmt::Result {
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(
+        out,
+        r#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{0}" xml:lang="{0}">"#,
+        escaped(&site.language)
+    )?;
+    writeln!(out, "<head>")?;
+    writeln!(out, r#"<meta charset="utf-8" />"#)?;
+    writeln!(out, "<title>{}</title>", escaped(title))?;
+    writeln!(out, r#"<link rel="stylesheet" href="style.css" />"#)?;
+    writeln!(out, "</head>")
+}
+
+/// Render the navigation document, which lists every chapter.
+fn nav(out: &mut String, site: &Site, book: &Book) -> fmt::Result {
+    head(out, site, &book.title)?;
+
+    writeln!(out, "<body>")?;
+    writeln!(out, r#"<nav epub:type="toc" id="toc">"#)?;
+    writeln!(out, "<h1>{}</h1>", escaped(&book.title))?;
+    writeln!(out, "<ol>")?;
+
+    for (index, chapter) in book.chapters.iter().enumerate() {
+        writeln!(
+            out,
+            r#"<li><a href="{}">{}</a></li>"#,
+            Chapter::file(index),
+            escaped(chapter.title)
+        )?;
+    }
+
+    writeln!(out, "</ol>")?;
+    writeln!(out, "</nav>")?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// Render the document of a chapter.
+///
+/// Links are made absolute, so that they lead to the site, except for links
+/// to chapters within the book, which lead to their document instead.
+fn chapter(out: &mut String, site: &Site, book: &Book, chapter: &Chapter) -> fmt::Result {
+    let mut body = feed::absolutize(chapter.body, &chapter.url);
+
+    for (index, other) in book.chapters.iter().enumerate() {
+        let file = Chapter::file(index);
+
+        for end in ['"', '#'] {
+            body = body.replace(
+                &format!(r#"="{}{end}"#, other.url),
+                &format!(r#"="{file}{end}"#),
+            );
+        }
+    }
+
+    head(out, site, chapter.title)?;
+
+    writeln!(out, "<body>")?;
+    writeln!(out, r#"<section epub:type="chapter">"#)?;
+    writeln!(out, "<h1>{}</h1>", escaped(chapter.title))?;
+
+    if let Some(created) = chapter.created {
+        writeln!(
+            out,
+            r#"<p class="published"><time datetime="{created}">{}</time></p>"#,
+            created.format("%Y-%m-%d")
+        )?;
+    }
+
+    writeln!(out, "{body}")?;
+    writeln!(out, "</section>")?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
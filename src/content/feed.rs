@@ -0,0 +1,426 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Write as _},
+    path::Path,
+};
+
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    query::{Changed, Or, With, Without},
+    system::{Commands, Query, Res},
+};
+use chrono::{DateTime, Utc};
+use url::Url;
+
+use crate::{
+    Now,
+    content::{
+        Output,
+        collection::{Collection, CollectionConfig},
+        head::Link,
+        page::{Body, Created, Description, Draft, Keywords, Modified, Page, Title},
+        route::Route,
+        taxonomy,
+    },
+    site::Site,
+    util::{self, escaped},
+};
+
+/// Marker component for generated feeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Feed;
+
+/// The file name of Atom feeds.
+pub const ATOM: &str = "atom.xml";
+
+/// The file name of RSS feeds.
+pub const RSS: &str = "rss.xml";
+
+/// The `link` tags that advertise the feeds published under `route`.
+#[must_use]
+pub fn links(title: &str, route: &Route) -> Vec<Link> {
+    vec![
+        Link {
+            rel: "alternate".into(),
+            href: format!("{}{ATOM}", route.0),
+            kind: Some("application/atom+xml".into()),
+            title: Some(title.into()),
+        },
+        Link {
+            rel: "alternate".into(),
+            href: format!("{}{RSS}", route.0),
+            kind: Some("application/rss+xml".into()),
+            title: Some(title.into()),
+        },
+    ]
+}
+
+/// A single entry within a feed.
+#[derive(Debug, Clone)]
+struct Entry<'a> {
+    title: &'a str,
+    description: &'a str,
+    url: Url,
+    published: Option<DateTime<Utc>>,
+    updated: DateTime<Utc>,
+    content: String,
+    categories: &'a [String],
+    collection: Option<Entity>,
+}
+
+/// A feed, and the entries that are in it.
+#[derive(Debug, Clone)]
+struct Channel<'a> {
+    title: String,
+    description: &'a str,
+    /// The route of the feed, and the page it is an alternative of.
+    route: Route,
+    entries: Vec<&'a Entry<'a>>,
+}
+
+/// Generate the site wide feeds, as well as a feed for every keyword and
+/// collection.
+pub fn generate(
+    pages: Query<
+        (
+            &Title,
+            &Description,
+            &Keywords,
+            &Route,
+            &Body,
+            Option<&Created>,
+            Option<&Modified>,
+            Option<&Collection>,
+        ),
+        (With<Page>, Without<Draft>),
+    >,
+    collections: Query<(Entity, &Title, &Description, &Route, &CollectionConfig)>,
+    changed: Query<(), (With<Page>, Or<(Changed<Body>, Changed<Collection>)>)>,
+    feeds: Query<Entity, With<Feed>>,
+    site: Res<Site>,
+    now: Res<Now>,
+    mut commands: Commands,
+) {
+    if changed.is_empty() {
+        return;
+    }
+
+    for feed in feeds {
+        commands.entity(feed).despawn();
+    }
+
+    let mut entries: Vec<Entry> = pages
+        .iter()
+        .filter(|(.., collection)| {
+            collection.is_none_or(|Collection(collection)| {
+                collections
+                    .get(*collection)
+                    .is_ok_and(|(.., config)| config.feed)
+            })
+        })
+        .map(
+            |(
+                Title(title),
+                Description(description),
+                Keywords(keywords),
+                route,
+                Body(body),
+                created,
+                modified,
+                collection,
+            )| {
+                let url = route.url(&site.url);
+                let published = created.map(|created| created.to_utc());
+
+                Entry {
+                    title,
+                    description,
+                    content: absolutize(body, &url),
+                    url,
+                    published,
+                    updated: modified
+                        .map(|modified| modified.to_utc())
+                        .or(published)
+                        .unwrap_or(now.0),
+                    categories: keywords,
+                    collection: collection.map(|Collection(collection)| *collection),
+                }
+            },
+        )
+        .collect();
+
+    // Newest first, with any undated entries last.
+    entries.sort_by_key(|entry| Reverse(entry.published));
+
+    let mut channels = vec![Channel {
+        title: site.title.clone(),
+        description: &site.description,
+        route: Route::root(),
+        entries: entries.iter().collect(),
+    }];
+
+    let mut tags = BTreeMap::<String, Channel>::new();
+
+    for entry in &entries {
+        // NOTE: Keywords may be spelled differently, yet share a tag, such
+        //       as `Rust` and `rust`.
+        let mut slugs = HashSet::new();
+
+        for keyword in entry.categories {
+            let slug = util::slugify(keyword);
+
+            if slug.is_empty() || !slugs.insert(slug.clone()) {
+                continue;
+            }
+
+            tags.entry(slug.clone())
+                .or_insert_with(|| Channel {
+                    title: format!("{} · {keyword}", site.title),
+                    description: &site.description,
+                    route: Route::new([taxonomy::TAGS, &slug]),
+                    entries: Vec::new(),
+                })
+                .entries
+                .push(entry);
+        }
+    }
+
+    channels.extend(tags.into_values());
+
+    for (collection, Title(title), Description(description), route, config) in &collections {
+        if !config.feed {
+            continue;
+        }
+
+        channels.push(Channel {
+            title: format!("{} · {title}", site.title),
+            description: match description.is_empty() {
+                true => &site.description,
+                false => description,
+            },
+            route: route.clone(),
+            entries: entries
+                .iter()
+                .filter(|entry| entry.collection == Some(collection))
+                .collect(),
+        });
+    }
+
+    for channel in channels {
+        let updated = channel
+            .entries
+            .iter()
+            .map(|entry| entry.updated)
+            .max()
+            .unwrap_or(now.0);
+
+        let mut atom = String::new();
+        let mut rss = String::new();
+
+        // NOTE: Writing to a `String` never fails.
+        let _ = self::atom(&mut atom, &site, &channel, updated);
+        let _ = self::rss(&mut rss, &site, &channel, updated);
+
+        let path = Path::new(channel.route.0.trim_start_matches('/'));
+
+        commands.spawn((
+            Feed,
+            Output::<Path>(path.join(ATOM).into()),
+            Output::<String>(atom),
+        ));
+        commands.spawn((
+            Feed,
+            Output::<Path>(path.join(RSS).into()),
+            Output::<String>(rss),
+        ));
+    }
+}
+
+/// Render an Atom feed.
+fn atom(out: &mut String, site: &Site, channel: &Channel, updated: DateTime<Utc>) -> fmt::Result {
+    let link = channel.route.url(&site.url);
+    let this = format!("{link}{ATOM}");
+
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="{}">"#,
+        escaped(&site.language)
+    )?;
+    writeln!(out, "<title>{}</title>", escaped(&channel.title))?;
+
+    if !channel.description.is_empty() {
+        writeln!(out, "<subtitle>{}</subtitle>", escaped(channel.description))?;
+    }
+
+    writeln!(out, "<id>{}</id>", escaped(link.as_str()))?;
+    writeln!(
+        out,
+        r#"<link rel="self" type="application/atom+xml" href="{}" />"#,
+        escaped(&this)
+    )?;
+    writeln!(
+        out,
+        r#"<link rel="alternate" type="text/html" href="{}" />"#,
+        escaped(link.as_str())
+    )?;
+    writeln!(out, "<updated>{}</updated>", updated.to_rfc3339())?;
+
+    writeln!(out, "<author>")?;
+    writeln!(out, "<name>{}</name>", escaped(&site.author.name))?;
+
+    if let Some(email) = &site.author.email {
+        writeln!(out, "<email>{}</email>", escaped(email))?;
+    }
+
+    if let Some(url) = &site.author.url {
+        writeln!(out, "<uri>{}</uri>", escaped(url.as_str()))?;
+    }
+
+    writeln!(out, "</author>")?;
+    writeln!(out, "<generator>glados</generator>")?;
+
+    for entry in &channel.entries {
+        writeln!(out, "<entry>")?;
+        writeln!(out, "<title>{}</title>", escaped(entry.title))?;
+        writeln!(out, "<id>{}</id>", escaped(entry.url.as_str()))?;
+        writeln!(
+            out,
+            r#"<link rel="alternate" type="text/html" href="{}" />"#,
+            escaped(entry.url.as_str())
+        )?;
+
+        if let Some(published) = entry.published {
+            writeln!(out, "<published>{}</published>", published.to_rfc3339())?;
+        }
+
+        writeln!(out, "<updated>{}</updated>", entry.updated.to_rfc3339())?;
+
+        if !entry.description.is_empty() {
+            writeln!(out, "<summary>{}</summary>", escaped(entry.description))?;
+        }
+
+        for category in entry.categories {
+            writeln!(out, r#"<category term="{}" />"#, escaped(category))?;
+        }
+
+        writeln!(
+            out,
+            r#"<content type="html">{}</content>"#,
+            escaped(&entry.content)
+        )?;
+        writeln!(out, "</entry>")?;
+    }
+
+    writeln!(out, "</feed>")
+}
+
+/// Render an RSS 2.0 feed.
+fn rss(out: &mut String, site: &Site, channel: &Channel, updated: DateTime<Utc>) -> fmt::Result {
+    let link = channel.route.url(&site.url);
+    let this = format!("{link}{RSS}");
+
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">"#
+    )?;
+    writeln!(out, "<channel>")?;
+    writeln!(out, "<title>{}</title>", escaped(&channel.title))?;
+    writeln!(out, "<link>{}</link>", escaped(link.as_str()))?;
+    // NOTE: RSS requires a description, even if it is empty.
+    writeln!(
+        out,
+        "<description>{}</description>",
+        escaped(channel.description)
+    )?;
+    writeln!(
+        out,
+        r#"<atom:link rel="self" type="application/rss+xml" href="{}" />"#,
+        escaped(&this)
+    )?;
+    writeln!(out, "<language>{}</language>", escaped(&site.language))?;
+    writeln!(
+        out,
+        "<lastBuildDate>{}</lastBuildDate>",
+        updated.to_rfc2822()
+    )?;
+    writeln!(out, "<generator>glados</generator>")?;
+
+    for entry in &channel.entries {
+        writeln!(out, "<item>")?;
+        writeln!(out, "<title>{}</title>", escaped(entry.title))?;
+        writeln!(out, "<link>{}</link>", escaped(entry.url.as_str()))?;
+        writeln!(
+            out,
+            r#"<guid isPermaLink="true">{}</guid>"#,
+            escaped(entry.url.as_str())
+        )?;
+
+        if let Some(published) = entry.published {
+            writeln!(out, "<pubDate>{}</pubDate>", published.to_rfc2822())?;
+        }
+
+        if !site.author.name.is_empty() {
+            writeln!(
+                out,
+                "<dc:creator>{}</dc:creator>",
+                escaped(&site.author.name)
+            )?;
+        }
+
+        writeln!(
+            out,
+            "<description>{}</description>",
+            escaped(entry.description)
+        )?;
+
+        for category in entry.categories {
+            writeln!(out, "<category>{}</category>", escaped(category))?;
+        }
+
+        writeln!(
+            out,
+            "<content:encoded>{}</content:encoded>",
+            escaped(&entry.content)
+        )?;
+        writeln!(out, "</item>")?;
+    }
+
+    writeln!(out, "</channel>")?;
+    writeln!(out, "</rss>")
+}
+
+/// Rewrite every `href` and `src` attribute in some HTML to be absolute,
+/// resolving them relative to `base`.
+#[must_use]
+pub fn absolutize(html: &str, base: &Url) -> String {
+    const ATTRIBUTES: [&str; 2] = [r#" href=""#, r#" src=""#];
+
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = ATTRIBUTES
+        .iter()
+        .filter_map(|attribute| Some(rest.find(attribute)? + attribute.len()))
+        .min()
+    {
+        let (head, tail) = rest.split_at(start);
+        let end = tail.find('"').unwrap_or(tail.len());
+        let (value, tail) = tail.split_at(end);
+
+        out.push_str(head);
+
+        match base.join(value) {
+            Ok(url) => out.push_str(url.as_str()),
+            Err(..) => out.push_str(value),
+        }
+
+        rest = tail;
+    }
+
+    out.push_str(rest);
+    out
+}
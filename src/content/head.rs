@@ -115,6 +115,29 @@ impl Render for OgType {
     }
 }
 
+/// A `link` tag, pointing to some related resource.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Link {
+    pub rel: String,
+    pub href: String,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl Render for Link {
+    fn render(&self) -> Markup {
+        html! {
+            link
+                rel = (self.rel)
+                href = (self.href)
+                type = [self.kind.as_deref()]
+                title = [self.title.as_deref()];
+        }
+    }
+}
+
 /// Stuff for generating the `head` tag.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Head {
@@ -122,6 +145,10 @@ pub struct Head {
     pub description: String,
     pub url: Url,
     pub og_type: OgType,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    #[serde(default)]
+    pub robots: Option<String>,
 }
 
 impl Render for Head {
@@ -142,6 +169,12 @@ impl Render for Head {
                     name = "author"
                     content = "Hera Chamorro";
 
+                @if let Some(robots) = &self.robots {
+                    meta
+                        name = "robots"
+                        content = (robots);
+                }
+
                 // # OpenGraph bullshit
                 meta
                     name = "og:url"
@@ -155,6 +188,10 @@ impl Render for Head {
                 // TODO: OpenGraph Image
                 (self.og_type)
 
+                @for link in &self.links {
+                    (link)
+                }
+
                 // // Preload stuff
                 // link
                 //     rel = "preload"
@@ -0,0 +1,303 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, anyhow};
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        error::Result,
+        query::{Changed, Has, With},
+        system::{Commands, Query, Res},
+    },
+    log::warn,
+};
+use chrono::{DateTime, FixedOffset};
+use gix::{ObjectId, bstr::ByteSlice as _};
+use maud::{Markup, html};
+use similar::{ChangeTag, TextDiff};
+
+use crate::{
+    content::{
+        Input, Output,
+        head::{Head, OgType},
+        layout::Layouts,
+        page::{Description, NoIndex, Page, Title},
+        route::Route,
+    },
+    site::Site,
+};
+
+/// Marker component for pages that have their revision history published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct History;
+
+/// A single revision of the source of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Revision {
+    /// The commit this revision was introduced in.
+    pub id: ObjectId,
+    /// When the commit was made.
+    pub time: DateTime<FixedOffset>,
+    /// The first line of the commit message.
+    pub summary: String,
+    /// The rest of the commit message, if any.
+    pub body: Option<String>,
+    /// The source of the page as of this revision.
+    pub source: String,
+}
+
+/// The revisions of a page, ordered from newest to oldest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Component)]
+pub struct Revisions(pub Vec<Revision>);
+
+/// The entity that holds the generated history page of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct HistoryPage(pub Entity);
+
+/// Read the revisions of every page with [`History`] from the git repository.
+///
+/// Pages outside of the working directory of the repository, or that haven't
+/// been committed yet, are skipped with a warning, as there's no history to
+/// publish for them.
+pub fn load(
+    query: Query<(Entity, &Input<Path>), (With<Page>, With<History>, Changed<Input<String>>)>,
+    mut commands: Commands,
+) -> Result<()> {
+    if query.is_empty() {
+        return Ok(());
+    }
+
+    let repo = match gix::discover(".") {
+        Ok(repo) => repo,
+        Err(error) => {
+            warn!("not publishing the history of pages, as there's no git repository: {error}");
+            return Ok(());
+        }
+    };
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("git repository has no working directory"))?
+        .canonicalize()
+        .context("resolving git working directory")?;
+
+    let mut pages = Vec::new();
+
+    for (entity, Input(path)) in query {
+        let relative = path
+            .canonicalize()
+            .with_context(|| format!("resolving {:?}", path.display()))?;
+
+        let Ok(relative) = relative.strip_prefix(&workdir) else {
+            warn!(
+                "not publishing the history of {:?}, as it's not within the git repository",
+                path.display()
+            );
+            commands.entity(entity).remove::<Revisions>();
+            continue;
+        };
+
+        pages.push((entity, path, PathBuf::from(relative), Vec::new()));
+    }
+
+    // NOTE: A repository without any commits has no history for any page.
+    let Ok(head) = repo.head_id() else {
+        for (entity, path, _, _) in pages {
+            warn!(
+                "not publishing the history of {:?}, as the git repository has no commits",
+                path.display()
+            );
+            commands.entity(entity).remove::<Revisions>();
+        }
+
+        return Ok(());
+    };
+
+    for info in repo.rev_walk([head.detach()]).all()? {
+        let commit = info?.object()?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent_ids().next() {
+            Some(parent) => Some(repo.find_commit(parent.detach())?.tree()?),
+            None => None,
+        };
+
+        for (_, _, path, revisions) in &mut pages {
+            let Some(entry) = tree.lookup_entry_by_path(&*path)? else {
+                continue;
+            };
+
+            let previous = match &parent_tree {
+                Some(parent_tree) => parent_tree.lookup_entry_by_path(&*path)?,
+                None => None,
+            };
+
+            // The file was left untouched by this commit.
+            if previous.is_some_and(|previous| previous.object_id() == entry.object_id()) {
+                continue;
+            }
+
+            let message = commit.message()?;
+            let time = commit.time()?;
+            let offset = FixedOffset::east_opt(time.offset)
+                .ok_or_else(|| anyhow!("invalid timezone offset in commit {}", commit.id()))?;
+            let time = DateTime::from_timestamp(time.seconds, 0)
+                .ok_or_else(|| anyhow!("invalid time in commit {}", commit.id()))?
+                .with_timezone(&offset);
+
+            let blob = repo.find_blob(entry.object_id())?;
+
+            revisions.push(Revision {
+                id: commit.id().detach(),
+                time,
+                summary: message.title.to_str_lossy().trim().to_owned(),
+                body: message
+                    .body
+                    .map(|body| body.to_str_lossy().trim().to_owned())
+                    .filter(|body| !body.is_empty()),
+                source: String::from_utf8_lossy(&blob.data).into_owned(),
+            });
+        }
+    }
+
+    for (entity, path, _, revisions) in pages {
+        if revisions.is_empty() {
+            warn!(
+                "not publishing the history of {:?}, as it hasn't been committed",
+                path.display()
+            );
+            commands.entity(entity).remove::<Revisions>();
+        } else {
+            commands.entity(entity).insert(Revisions(revisions));
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate the `history/` sub-page of every page with [`Revisions`], with the
+/// layout of generated pages, see [`Site::listing_layout`].
+pub fn render(
+    query: Query<
+        (
+            Entity,
+            &Revisions,
+            &Title,
+            &Description,
+            &Route,
+            Option<&HistoryPage>,
+            Has<NoIndex>,
+        ),
+        Changed<Revisions>,
+    >,
+    site: Res<Site>,
+    layouts: Res<Layouts>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (
+        entity,
+        Revisions(revisions),
+        Title(title),
+        Description(description),
+        route,
+        history,
+        noindex,
+    ) in query
+    {
+        let history_route = route.join("history");
+        let head = Head {
+            title: format!("History of {title}"),
+            description: description.clone(),
+            url: history_route.url(&site.url),
+            og_type: OgType::Website,
+            links: Vec::new(),
+            robots: noindex.then(|| "noindex".into()),
+        };
+
+        let main = html! {
+            div.history {
+                h1 { "History of " a href = (route.0) { (title) } }
+
+                @for (index, revision) in revisions.iter().enumerate() {
+                    @let previous = revisions
+                        .get(index + 1)
+                        .map(|revision| &*revision.source)
+                        .unwrap_or("");
+
+                    article.revision id = (revision.id) {
+                        header {
+                            h2 { (revision.summary) }
+
+                            time datetime = (revision.time.to_rfc3339()) {
+                                (revision.time.format("%Y-%m-%d %H:%M %:z"))
+                            }
+                            " · "
+                            code { (revision.id.to_hex_with_len(7)) }
+                        }
+
+                        @if let Some(body) = &revision.body {
+                            p.message { (body) }
+                        }
+
+                        (diff(previous, &revision.source))
+                    }
+                }
+            }
+        };
+
+        let document = layouts.render_generated(
+            site.listing_layout.as_deref(),
+            &site,
+            &head,
+            &head.title,
+            &history_route,
+            main,
+        )?;
+
+        let target = match history {
+            Some(&HistoryPage(target)) => target,
+            None => {
+                let target = commands.spawn_empty().id();
+
+                commands.entity(entity).insert(HistoryPage(target));
+
+                target
+            }
+        };
+
+        commands.entity(target).insert((
+            history_route.clone(),
+            Output::<Path>(history_route.file().into()),
+            Output::<String>(document.into_string()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Render a unified diff between two revisions of some source.
+fn diff(old: &str, new: &str) -> Markup {
+    let diff = TextDiff::from_lines(old, new);
+    let mut unified = diff.unified_diff();
+
+    unified.context_radius(3);
+
+    html! {
+        pre.diff {
+            code {
+                @for hunk in unified.iter_hunks() {
+                    span.diff-hunk { (hunk.header()) "\n" }
+
+                    @for change in hunk.iter_changes() {
+                        @let line = change.value().trim_end_matches(['\r', '\n']);
+
+                        @match change.tag() {
+                            ChangeTag::Delete => { del { "-" (line) } "\n" }
+                            ChangeTag::Insert => { ins { "+" (line) } "\n" }
+                            ChangeTag::Equal => { span { " " (line) } "\n" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
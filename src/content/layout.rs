@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use bevy::ecs::{component::Component, resource::Resource};
+use chrono::NaiveDate;
+use maud::{DOCTYPE, Markup, PreEscaped, Render, html};
+
+use crate::{
+    content::{head::Head, route::Route, taxonomy, toc::TableOfContents},
+    site::Site,
+};
+
+/// The name of the layout a page is rendered with.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct PageLayout(pub String);
+
+/// Everything a layout has access to when rendering a page.
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    pub site: &'a Site,
+    pub head: &'a Head,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub keywords: &'a [String],
+    pub route: &'a Route,
+    pub created: Option<NaiveDate>,
+    pub modified: Option<NaiveDate>,
+    /// The table of contents of the page, unless it has been disabled.
+    pub toc: Option<&'a TableOfContents>,
+    /// The route of the PDF exported from the page, if there is one.
+    pub pdf: Option<&'a str>,
+    /// The route of the revision history of the page, if it's published.
+    pub history: Option<&'a str>,
+    /// The rendered body of the page.
+    pub body: &'a str,
+}
+
+/// Trait for layouts, which render a page into a complete document.
+pub trait Layout: Send + Sync + 'static {
+    fn render(&self, context: &Context) -> Markup;
+}
+
+impl<F: Fn(&Context) -> Markup + Send + Sync + 'static> Layout for F {
+    #[inline]
+    fn render(&self, context: &Context) -> Markup {
+        self(context)
+    }
+}
+
+/// The layouts pages may select by name.
+#[derive(Resource)]
+pub struct Layouts(HashMap<String, Box<dyn Layout>>);
+
+impl Layouts {
+    /// The name of the layout used when nothing else is selected.
+    pub const DEFAULT: &str = "page";
+
+    /// The name of the layout used by generated pages, such as listings, when
+    /// nothing else is selected.
+    pub const LISTING: &str = "listing";
+
+    /// Register a layout under `name`, replacing any previous one.
+    pub fn register(&mut self, name: impl Into<String>, layout: impl Layout) -> &mut Self {
+        self.0.insert(name.into(), Box::new(layout));
+        self
+    }
+
+    /// Get the layout registered under `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Layout> {
+        self.0.get(name).map(|layout| &**layout)
+    }
+
+    /// Render a generated page, such as a listing, with the layout `name`,
+    /// falling back to [`Layouts::LISTING`].
+    ///
+    /// Unlike the body of a page, `main` already holds the heading of the
+    /// generated page.
+    pub fn render_generated(
+        &self,
+        name: Option<&str>,
+        site: &Site,
+        head: &Head,
+        title: &str,
+        route: &Route,
+        main: Markup,
+    ) -> anyhow::Result<Markup> {
+        let name = name.unwrap_or(Layouts::LISTING);
+        let layout = self
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown layout, {name:?} for {:?}", route.0))?;
+
+        Ok(layout.render(&Context {
+            site,
+            head,
+            title,
+            description: &head.description,
+            keywords: &[],
+            route,
+            created: None,
+            modified: None,
+            toc: None,
+            pdf: None,
+            history: None,
+            body: &main.into_string(),
+        }))
+    }
+}
+
+impl Default for Layouts {
+    fn default() -> Self {
+        let mut layouts = Layouts(HashMap::new());
+
+        layouts
+            .register(Layouts::DEFAULT, page)
+            .register("post", post)
+            .register(Layouts::LISTING, listing);
+        layouts
+    }
+}
+
+/// A plain page, with just a title, tags, and its body.
+#[must_use]
+pub fn page(context: &Context) -> Markup {
+    let main = html! {
+        article {
+            header {
+                h1 { (context.title) }
+                (tags(context.keywords))
+                (pdf(context.pdf))
+                (history(context.history))
+            }
+
+            @if let Some(toc) = context.toc {
+                (toc)
+            }
+
+            (PreEscaped(context.body))
+        }
+    };
+
+    document(context.site, context.head, main)
+}
+
+/// A post, which also shows when it was published and last modified.
+#[must_use]
+pub fn post(context: &Context) -> Markup {
+    let main = html! {
+        article.post {
+            header {
+                h1 { (context.title) }
+
+                @if let Some(created) = context.created {
+                    p.published {
+                        "Published "
+                        time datetime = (created) { (created.format("%Y-%m-%d")) }
+
+                        @if let Some(modified) = context.modified.filter(|modified| *modified != created) {
+                            ", updated "
+                            time datetime = (modified) { (modified.format("%Y-%m-%d")) }
+                        }
+                    }
+                }
+
+                (tags(context.keywords))
+                (pdf(context.pdf))
+                (history(context.history))
+            }
+
+            @if let Some(toc) = context.toc {
+                (toc)
+            }
+
+            (PreEscaped(context.body))
+        }
+    };
+
+    document(context.site, context.head, main)
+}
+
+/// A generated page, such as a listing, whose body already holds its heading.
+#[must_use]
+pub fn listing(context: &Context) -> Markup {
+    document(
+        context.site,
+        context.head,
+        html! { (PreEscaped(context.body)) },
+    )
+}
+
+/// Render a link to the PDF exported from a page, if there is one.
+#[must_use]
+pub fn pdf(route: Option<&str>) -> Markup {
+    html! {
+        @if let Some(route) = route {
+            p.pdf {
+                a href = (route) type = "application/pdf" { "Download as PDF" }
+            }
+        }
+    }
+}
+
+/// Render a link to the revision history of a page, if it's published.
+#[must_use]
+pub fn history(route: Option<&str>) -> Markup {
+    html! {
+        @if let Some(route) = route {
+            p.history {
+                a href = (route) { "History" }
+            }
+        }
+    }
+}
+
+/// Render links to the tag pages of `keywords`.
+#[must_use]
+pub fn tags(keywords: &[String]) -> Markup {
+    html! {
+        @if !keywords.is_empty() {
+            ul.tags {
+                @for keyword in keywords {
+                    li {
+                        a href = (taxonomy::route(taxonomy::TAGS, keyword).0) { (keyword) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Wrap `main` in the document shell shared by every page of the site.
+#[must_use]
+pub fn document(site: &Site, head: &Head, main: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang = (site.language) {
+            (head)
+
+            body {
+                header {
+                    nav {
+                        a href = "/" { (site.title) }
+                    }
+                }
+
+                main {
+                    (main)
+                }
+
+                footer {
+                    @if !site.author.name.is_empty() {
+                        p { "© " (site.author.name) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A summary of a page, as shown within listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Summary<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    pub route: &'a Route,
+    pub created: Option<NaiveDate>,
+}
+
+impl Render for Summary<'_> {
+    fn render(&self) -> Markup {
+        html! {
+            article.summary {
+                h2 {
+                    a href = (self.route.0) { (self.title) }
+                }
+
+                @if let Some(created) = self.created {
+                    time datetime = (created) { (created.format("%Y-%m-%d")) }
+                }
+
+                @if !self.description.is_empty() {
+                    p { (self.description) }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, Or, With, Without},
+    system::{Commands, Query, Res},
+};
+use chrono::Datelike as _;
+use maud::{Markup, PreEscaped, html};
+
+use crate::{
+    content::{
+        Output,
+        collection::{Collection, CollectionConfig, Sort},
+        feed,
+        head::{Head, Link, OgType},
+        layout::{Layouts, Summary},
+        page::{Body, Created, Description, Draft, Page, Title},
+        route::Route,
+    },
+    site::Site,
+};
+
+/// Marker component for generated listings, such as the home page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Listing;
+
+/// The route of the chronological archive.
+pub const ARCHIVE: &str = "archive";
+
+/// The route of the `number`th page of the listing under `route`, counting
+/// from zero.
+#[must_use]
+pub fn paginate(route: &Route, number: usize) -> Route {
+    match number {
+        0 => route.clone(),
+        number => Route::new([&*route.0, "page", &(number + 1).to_string()]),
+    }
+}
+
+/// A listing to be split across several pages.
+struct Pages<'a> {
+    title: &'a str,
+    description: &'a str,
+    route: &'a Route,
+    /// The introduction shown above the first page.
+    intro: Option<&'a str>,
+    /// The title of the feeds published for this listing, if there are any.
+    feed: Option<String>,
+    per_page: usize,
+    /// The layout the pages are rendered with, if not the default one.
+    layout: Option<&'a str>,
+    summaries: Vec<Summary<'a>>,
+}
+
+/// Generate the home page, the archive, and the listings of every collection.
+pub fn generate(
+    pages: Query<
+        (
+            &Title,
+            &Description,
+            &Route,
+            Option<&Created>,
+            Option<&Collection>,
+        ),
+        (With<Page>, Without<Draft>),
+    >,
+    collections: Query<(
+        Entity,
+        &Title,
+        &Description,
+        &Route,
+        &CollectionConfig,
+        Option<&Body>,
+    )>,
+    changed: Query<
+        (),
+        Or<(
+            (
+                With<Page>,
+                Or<(
+                    Changed<Title>,
+                    Changed<Description>,
+                    Changed<Route>,
+                    Changed<Created>,
+                    Changed<Collection>,
+                    Changed<Draft>,
+                )>,
+            ),
+            Changed<CollectionConfig>,
+            Changed<Body>,
+        )>,
+    >,
+    listings: Query<Entity, With<Listing>>,
+    layouts: Res<Layouts>,
+    site: Res<Site>,
+    mut commands: Commands,
+) -> Result<()> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    for listing in listings {
+        commands.entity(listing).despawn();
+    }
+
+    let summaries: Vec<(Summary, Option<Entity>)> = pages
+        .iter()
+        .map(
+            |(Title(title), Description(description), route, created, collection)| {
+                let summary = Summary {
+                    title,
+                    description,
+                    route,
+                    created: created.map(|created| created.0),
+                };
+
+                (
+                    summary,
+                    collection.map(|Collection(collection)| *collection),
+                )
+            },
+        )
+        .collect();
+
+    let mut listings = Vec::new();
+
+    for (entity, Title(title), Description(description), route, config, body) in &collections {
+        let mut summaries: Vec<Summary> = summaries
+            .iter()
+            .filter(|(_, collection)| *collection == Some(entity))
+            .map(|(summary, _)| *summary)
+            .collect();
+
+        summaries.sort_by(|lhs, rhs| config.sort.compare(lhs, rhs));
+
+        listings.push(Pages {
+            title,
+            description,
+            route,
+            intro: body.map(|Body(body)| &**body),
+            feed: config.feed.then(|| format!("{} · {title}", site.title)),
+            per_page: config.paginate,
+            layout: config
+                .listing_layout
+                .as_deref()
+                .or(site.listing_layout.as_deref()),
+            summaries,
+        });
+    }
+
+    let mut all: Vec<Summary> = summaries.iter().map(|(summary, _)| *summary).collect();
+
+    all.sort_by(|lhs, rhs| Sort::Newest.compare(lhs, rhs));
+
+    let root = Route::root();
+
+    // NOTE: A page may take the place of the home page.
+    if !summaries.iter().any(|(summary, _)| *summary.route == root) {
+        listings.push(Pages {
+            title: &site.title,
+            description: &site.description,
+            route: &root,
+            intro: None,
+            feed: Some(site.title.clone()),
+            per_page: site.paginate,
+            layout: site.listing_layout.as_deref(),
+            summaries: all.clone(),
+        });
+    }
+
+    for listing in &listings {
+        spawn_pages(listing, &layouts, &site, &mut commands)?;
+    }
+
+    let route = Route::new([ARCHIVE]);
+    let main = archive(&all);
+
+    let head = Head {
+        title: format!("Archive · {}", site.title),
+        description: site.description.clone(),
+        url: route.url(&site.url),
+        og_type: OgType::Website,
+        links: Vec::new(),
+        robots: None,
+    };
+
+    let document = layouts.render_generated(
+        site.listing_layout.as_deref(),
+        &site,
+        &head,
+        "Archive",
+        &route,
+        main,
+    )?;
+
+    commands.spawn((
+        Listing,
+        Output::<Path>(route.file().into()),
+        Output::<String>(document.into_string()),
+        route,
+    ));
+
+    Ok(())
+}
+
+/// Spawn every page of a listing.
+fn spawn_pages(
+    listing: &Pages,
+    layouts: &Layouts,
+    site: &Site,
+    commands: &mut Commands,
+) -> anyhow::Result<()> {
+    let chunks: Vec<&[Summary]> = match listing.summaries.is_empty() {
+        true => vec![&[]],
+        false => listing.summaries.chunks(listing.per_page.max(1)).collect(),
+    };
+
+    for (number, chunk) in chunks.iter().enumerate() {
+        let route = paginate(listing.route, number);
+        let prev = number
+            .checked_sub(1)
+            .map(|prev| paginate(listing.route, prev));
+        let next = (number + 1 < chunks.len()).then(|| paginate(listing.route, number + 1));
+
+        let main = html! {
+            div.listing {
+                h1 { (listing.title) }
+
+                @if let (0, Some(intro)) = (number, listing.intro) {
+                    div.intro { (PreEscaped(intro)) }
+                }
+
+                @for summary in *chunk {
+                    (summary)
+                }
+
+                @if chunks.len() > 1 {
+                    nav.pagination {
+                        @if let Some(prev) = &prev {
+                            a rel = "prev" href = (prev.0) { "Previous" }
+                        }
+
+                        span { "Page " (number + 1) " of " (chunks.len()) }
+
+                        @if let Some(next) = &next {
+                            a rel = "next" href = (next.0) { "Next" }
+                        }
+                    }
+                }
+            }
+        };
+
+        let mut links = match &listing.feed {
+            Some(title) => feed::links(title, listing.route),
+            None => Vec::new(),
+        };
+
+        for (rel, route) in [("prev", prev), ("next", next)] {
+            if let Some(route) = route {
+                links.push(Link {
+                    rel: rel.into(),
+                    href: route.0,
+                    kind: None,
+                    title: None,
+                });
+            }
+        }
+
+        let title = match (number, *listing.route == Route::root()) {
+            (0, true) => listing.title.to_owned(),
+            (0, false) => format!("{} · {}", listing.title, site.title),
+            (number, true) => format!("Page {} · {}", number + 1, site.title),
+            (number, false) => format!("{} · Page {} · {}", listing.title, number + 1, site.title),
+        };
+
+        let head = Head {
+            title,
+            description: listing.description.to_owned(),
+            url: route.url(&site.url),
+            og_type: OgType::Website,
+            links,
+            robots: None,
+        };
+
+        let document =
+            layouts.render_generated(listing.layout, site, &head, listing.title, &route, main)?;
+
+        commands.spawn((
+            Listing,
+            Output::<Path>(route.file().into()),
+            Output::<String>(document.into_string()),
+            route,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Render the archive of every dated page, grouped by year and month.
+fn archive(summaries: &[Summary]) -> Markup {
+    let mut years: BTreeMap<i32, BTreeMap<u32, Vec<&Summary>>> = BTreeMap::new();
+
+    for summary in summaries {
+        let Some(created) = summary.created else {
+            continue;
+        };
+
+        years
+            .entry(created.year())
+            .or_default()
+            .entry(created.month())
+            .or_default()
+            .push(summary);
+    }
+
+    html! {
+        div.archive {
+            h1 { "Archive" }
+
+            @for (year, months) in years.iter().rev() {
+                section {
+                    h2 { (year) }
+
+                    @for (month, summaries) in months.iter().rev() {
+                        section {
+                            h3 id = (format!("{year}-{month:02}")) {
+                                @if let Some(created) = summaries.first().and_then(|summary| summary.created) {
+                                    (created.format("%B"))
+                                }
+                            }
+
+                            ul {
+                                @for summary in summaries {
+                                    li {
+                                        @if let Some(created) = summary.created {
+                                            time datetime = (created) { (created.format("%Y-%m-%d")) }
+                                            " "
+                                        }
+
+                                        a href = (summary.route.0) { (summary.title) }
+
+                                        @if !summary.description.is_empty() {
+                                            " — "
+                                            span.description { (summary.description) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
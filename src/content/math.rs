@@ -1,5 +1,91 @@
-use crate::content::Content;
-use bevy::ecs::component::Component;
+use std::{borrow::Cow, path::Path};
+
+use bevy::ecs::{
+    change_detection::{DetectChanges as _, Ref},
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, Has},
+    system::{Commands, Query, Res, ResMut},
+};
+use jotdown::{AttributeKind, Attributes, Container, Event};
+use serde::{Deserialize, Serialize};
+use typst::diag::{Severity, Warned};
+
+use crate::{
+    content::{
+        Content, Input,
+        djot::{DjotEvents, DjotOffsets},
+        math::latex::{Error, Translation},
+        page::PageOffset,
+    },
+    site::Site,
+    typst::{
+        diagnostic::{Diagnostic, Diagnostics, Origin},
+        inputs::Inputs,
+        world::{Compiled, TypstWorld},
+    },
+};
+
+pub mod latex;
+pub mod mathml;
+
+/// The syntax math is written in, as set by `math` within the front matter
+/// of a page.
+///
+/// A single span may be written in either with the `latex` or `typst` class,
+/// such as:
+///
+/// ```djot
+/// $`\frac{1}{2}`{.latex}
+/// ```
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Component,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MathSyntax {
+    #[default]
+    Typst,
+    /// LaTeX, which is translated to typst, see [`latex`].
+    Latex,
+}
+
+/// How math is rendered, as set by `math_output` within the site config or
+/// the front matter of a page.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Component,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MathOutput {
+    /// Math is rendered to SVG with typst, along with MathML that's hidden
+    /// from view but not from screen readers.
+    #[default]
+    Svg,
+    /// Math is converted to MathML, see [`mathml`].
+    MathMl,
+}
 
 /// A component for processing math.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Component)]
@@ -7,3 +93,272 @@ pub enum Math<C: Content + ?Sized = str> {
     Inline(C::Output),
     Block(C::Output),
 }
+
+/// Relates a math span to the page it's within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+#[relationship(relationship_target = MathSpans)]
+pub struct MathOf(pub Entity);
+
+/// The math spans within a page.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+#[relationship_target(relationship = MathOf, linked_spawn)]
+pub struct MathSpans(Vec<Entity>);
+
+/// The position of the event that starts a math span within its page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct MathPosition(pub usize);
+
+/// Where the source of a math span starts within its page, relative to its
+/// [`PageOffset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct MathOffset(pub usize);
+
+/// Math rendered to SVG.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Svg(pub String);
+
+/// Marker component for math spans written in LaTeX, whose [`Math`] has been
+/// translated to typst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Latex;
+
+/// Math converted to MathML.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct MathMl(pub String);
+
+/// Returns the syntax of a math span, as set by its class if it has one.
+#[must_use]
+pub fn syntax(attributes: &Attributes<'static>, page: MathSyntax) -> MathSyntax {
+    attributes
+        .iter()
+        .filter(|(kind, _)| matches!(kind, AttributeKind::Class))
+        .find_map(
+            |(_, value)| match value.parts().collect::<String>().as_str() {
+                "latex" => Some(MathSyntax::Latex),
+                "typst" => Some(MathSyntax::Typst),
+                _ => None,
+            },
+        )
+        .unwrap_or(page)
+}
+
+/// Spawn an entity for every math span within each page, translating any
+/// written in LaTeX, and reporting whatever couldn't be translated at the
+/// span within its page.
+pub fn extract(
+    query: Query<
+        (
+            Entity,
+            &DjotEvents,
+            &DjotOffsets,
+            Option<&MathSyntax>,
+            &Input<Path>,
+            &Input<String>,
+            &PageOffset,
+        ),
+        Changed<DjotEvents>,
+    >,
+    site: Res<Site>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut commands: Commands,
+) {
+    for (
+        page,
+        DjotEvents(events),
+        DjotOffsets(offsets),
+        page_syntax,
+        Input(path),
+        Input(contents),
+        &PageOffset(page_offset),
+    ) in query
+    {
+        commands.entity(page).despawn_related::<MathSpans>();
+
+        for (position, event) in events.iter().enumerate() {
+            let Event::Start(Container::Math { display }, attributes) = event else {
+                continue;
+            };
+
+            let mut text = String::new();
+
+            for event in &events[position + 1..] {
+                match event {
+                    Event::Str(s) => text.push_str(s),
+                    Event::End(..) => break,
+                    _ => {}
+                }
+            }
+
+            let offset = offsets
+                .get(position + 1)
+                .or_else(|| offsets.get(position))
+                .map_or(0, |range| range.start);
+
+            let syntax = self::syntax(attributes, page_syntax.copied().unwrap_or_default());
+
+            if syntax == MathSyntax::Latex {
+                let Translation { typst, errors } = latex::translate(&text, &site.latex_macros);
+
+                let origin = Origin {
+                    path,
+                    contents,
+                    start: 0,
+                    offset: page_offset + offset,
+                };
+
+                for Error {
+                    message,
+                    hints,
+                    range,
+                } in errors
+                {
+                    diagnostics.push(Diagnostic::at(
+                        Severity::Error,
+                        message,
+                        hints,
+                        Some(range),
+                        origin,
+                    ));
+                }
+
+                text = typst;
+            }
+
+            let math = match display {
+                true => Math::<str>::Block(Cow::Owned(text)),
+                false => Math::<str>::Inline(Cow::Owned(text)),
+            };
+
+            let mut entity = commands.spawn((
+                MathOf(page),
+                MathPosition(position),
+                MathOffset(offset),
+                math,
+            ));
+
+            if syntax == MathSyntax::Latex {
+                entity.insert(Latex);
+            }
+        }
+    }
+}
+
+/// The typst source of a math span, along with where the math starts within
+/// it.
+///
+/// Equations aren't numbered by typst, as their number is shown next to them
+/// instead, see [`Labels`](crate::content::numbering::Labels).
+#[must_use]
+pub fn source(math: &Math) -> (String, usize) {
+    let mut source = String::from(
+        "#set page(width: auto, height: auto, margin: 0pt, fill: none)\n\
+         #set text(size: 12pt)\n",
+    );
+
+    let (math, open, close) = match math {
+        Math::Inline(math) => (math, "$", "$"),
+        Math::Block(math) => (math, "$ ", " $"),
+    };
+
+    source.push_str(open);
+
+    let start = source.len();
+
+    source.push_str(math);
+    source.push_str(close);
+
+    (source, start)
+}
+
+/// Convert every math span to MathML.
+///
+/// Spans are converted even when they're also rendered to SVG, as the MathML
+/// is kept alongside the SVG for screen readers, hidden from view, and the
+/// SVG itself is hidden from screen readers.
+pub fn convert(query: Query<(Entity, &Math), Changed<Math>>, mut commands: Commands) {
+    for (entity, math) in query {
+        let mathml = match math {
+            Math::Inline(math) => mathml::convert(math, false),
+            Math::Block(math) => mathml::convert(math, true),
+        };
+
+        commands.entity(entity).insert(MathMl(mathml));
+    }
+}
+
+/// Look up `key` within a table.
+fn lookup<'t>(table: &[(&str, &'t str)], key: &str) -> Option<&'t str> {
+    table
+        .iter()
+        .find(|&&(name, _)| name == key)
+        .map(|&(_, value)| value)
+}
+
+/// Render every math span to SVG with typst, reporting any diagnostics at
+/// the span within its page.
+///
+/// Math within pages rendered to MathML is skipped, see [`MathOutput`]. Math
+/// is rendered again whenever the inputs of its page change, as it may refer
+/// to them.
+pub fn render(
+    query: Query<(Entity, &MathOf, Ref<Math>, &MathOffset, Has<Latex>)>,
+    pages: Query<(
+        &Input<Path>,
+        &Input<String>,
+        &PageOffset,
+        Ref<Inputs>,
+        Option<&MathOutput>,
+    )>,
+    world: Res<TypstWorld>,
+    site: Res<Site>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (entity, &MathOf(page), math, &MathOffset(offset), latex) in query {
+        let (Input(path), Input(contents), &PageOffset(page_offset), inputs, output) =
+            pages.get(page)?;
+
+        if !math.is_changed() && !inputs.is_changed() {
+            continue;
+        }
+
+        if output.copied().unwrap_or(site.math_output) == MathOutput::MathMl {
+            commands.entity(entity).remove::<Svg>();
+            continue;
+        }
+
+        let (source, start) = source(&math);
+        let main = TypstWorld::source("/math.typ", source);
+        let Compiled {
+            warned: Warned { output, warnings },
+            dependencies,
+        } = world.compile_svg(&main, &inputs);
+
+        // NOTE: Math written in LaTeX has been translated, so diagnostics
+        //       can't be located within it.
+        let origin = Origin {
+            path,
+            contents,
+            start: match latex {
+                true => usize::MAX,
+                false => start,
+            },
+            offset: page_offset + offset,
+        };
+
+        diagnostics.report(&warnings, &main, origin);
+        commands.entity(entity).insert(dependencies);
+
+        match output {
+            Ok(svg) => {
+                commands.entity(entity).insert(Svg(svg));
+            }
+            Err(errors) => {
+                diagnostics.report(&errors, &main, origin);
+                commands.entity(entity).remove::<Svg>();
+            }
+        }
+    }
+
+    Ok(())
+}
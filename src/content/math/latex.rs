@@ -0,0 +1,1266 @@
+//! Translation of LaTeX math to typst math.
+//!
+//! Only math is understood, as written between the delimiters of a math span
+//! in djot. Macros are translated to their counterpart in typst where there
+//! is one, and reported otherwise, rather than guessed at. Macros defined by
+//! the site are expanded beforehand, see
+//! [`Site::latex_macros`](crate::site::Site::latex_macros).
+
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::content::math::lookup;
+
+/// Macros that translate to a symbol or function of the same meaning.
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "alpha"),
+    ("beta", "beta"),
+    ("gamma", "gamma"),
+    ("delta", "delta"),
+    ("epsilon", "epsilon.alt"),
+    ("varepsilon", "epsilon"),
+    ("zeta", "zeta"),
+    ("eta", "eta"),
+    ("theta", "theta"),
+    ("vartheta", "theta.alt"),
+    ("iota", "iota"),
+    ("kappa", "kappa"),
+    ("varkappa", "kappa.alt"),
+    ("lambda", "lambda"),
+    ("mu", "mu"),
+    ("nu", "nu"),
+    ("xi", "xi"),
+    ("omicron", "omicron"),
+    ("pi", "pi"),
+    ("varpi", "pi.alt"),
+    ("rho", "rho"),
+    ("varrho", "rho.alt"),
+    ("sigma", "sigma"),
+    ("varsigma", "sigma.alt"),
+    ("tau", "tau"),
+    ("upsilon", "upsilon"),
+    ("phi", "phi.alt"),
+    ("varphi", "phi"),
+    ("chi", "chi"),
+    ("psi", "psi"),
+    ("omega", "omega"),
+    ("Gamma", "Gamma"),
+    ("Delta", "Delta"),
+    ("Theta", "Theta"),
+    ("Lambda", "Lambda"),
+    ("Xi", "Xi"),
+    ("Pi", "Pi"),
+    ("Sigma", "Sigma"),
+    ("Upsilon", "Upsilon"),
+    ("Phi", "Phi"),
+    ("Psi", "Psi"),
+    ("Omega", "Omega"),
+    ("cdot", "dot.op"),
+    ("times", "times"),
+    ("div", "div"),
+    ("pm", "plus.minus"),
+    ("mp", "minus.plus"),
+    ("ast", "ast"),
+    ("star", "star"),
+    ("circ", "compose"),
+    ("bullet", "bullet"),
+    ("oplus", "plus.circle"),
+    ("otimes", "times.circle"),
+    ("setminus", "without"),
+    ("mid", "divides"),
+    ("leq", "lt.eq"),
+    ("le", "lt.eq"),
+    ("geq", "gt.eq"),
+    ("ge", "gt.eq"),
+    ("neq", "eq.not"),
+    ("ne", "eq.not"),
+    ("ll", "lt.double"),
+    ("gg", "gt.double"),
+    ("approx", "approx"),
+    ("equiv", "equiv"),
+    ("sim", "tilde.op"),
+    ("simeq", "tilde.eq"),
+    ("cong", "tilde.equiv"),
+    ("propto", "prop"),
+    ("coloneqq", "colon.eq"),
+    ("in", "in"),
+    ("notin", "in.not"),
+    ("ni", "in.rev"),
+    ("subset", "subset"),
+    ("subseteq", "subset.eq"),
+    ("supset", "supset"),
+    ("supseteq", "supset.eq"),
+    ("cup", "union"),
+    ("cap", "inter"),
+    ("land", "and"),
+    ("wedge", "and"),
+    ("lor", "or"),
+    ("vee", "or"),
+    ("neg", "not"),
+    ("lnot", "not"),
+    ("forall", "forall"),
+    ("exists", "exists"),
+    ("nexists", "exists.not"),
+    ("perp", "perp"),
+    ("parallel", "parallel"),
+    ("models", "models"),
+    ("vdash", "tack.r"),
+    ("to", "arrow.r"),
+    ("rightarrow", "arrow.r"),
+    ("longrightarrow", "arrow.r.long"),
+    ("Rightarrow", "arrow.r.double"),
+    ("Longrightarrow", "arrow.r.double.long"),
+    ("implies", "arrow.r.double.long"),
+    ("mapsto", "arrow.r.bar"),
+    ("hookrightarrow", "arrow.r.hook"),
+    ("gets", "arrow.l"),
+    ("leftarrow", "arrow.l"),
+    ("longleftarrow", "arrow.l.long"),
+    ("Leftarrow", "arrow.l.double"),
+    ("Longleftarrow", "arrow.l.double.long"),
+    ("impliedby", "arrow.l.double.long"),
+    ("leftrightarrow", "arrow.l.r"),
+    ("Leftrightarrow", "arrow.l.r.double"),
+    ("iff", "arrow.l.r.double.long"),
+    ("uparrow", "arrow.t"),
+    ("downarrow", "arrow.b"),
+    ("sum", "sum"),
+    ("prod", "product"),
+    ("coprod", "product.co"),
+    ("int", "integral"),
+    ("iint", "integral.double"),
+    ("iiint", "integral.triple"),
+    ("oint", "integral.cont"),
+    ("bigcup", "union.big"),
+    ("bigcap", "inter.big"),
+    ("bigwedge", "and.big"),
+    ("bigvee", "or.big"),
+    ("bigoplus", "plus.circle.big"),
+    ("bigotimes", "times.circle.big"),
+    ("infty", "infinity"),
+    ("partial", "partial"),
+    ("nabla", "nabla"),
+    ("emptyset", "emptyset"),
+    ("varnothing", "emptyset"),
+    ("ldots", "dots.h"),
+    ("dots", "dots.h"),
+    ("cdots", "dots.c"),
+    ("vdots", "dots.v"),
+    ("ddots", "dots.down"),
+    ("prime", "prime"),
+    ("angle", "angle"),
+    ("aleph", "aleph"),
+    ("ell", "ell"),
+    ("hbar", "planck.reduce"),
+    ("Re", "Re"),
+    ("Im", "Im"),
+    ("top", "top"),
+    ("bot", "bot"),
+    ("langle", "angle.l"),
+    ("rangle", "angle.r"),
+    ("lfloor", "floor.l"),
+    ("rfloor", "floor.r"),
+    ("lceil", "ceil.l"),
+    ("rceil", "ceil.r"),
+    ("|", "||"),
+    ("Vert", "||"),
+    ("lVert", "||"),
+    ("rVert", "||"),
+    ("vert", "|"),
+    ("lvert", "|"),
+    ("rvert", "|"),
+    ("{", "\\{"),
+    ("}", "\\}"),
+    ("$", "\\$"),
+    ("#", "\\#"),
+    ("&", "\\&"),
+    ("_", "\\_"),
+    ("%", "%"),
+    ("bmod", "mod"),
+    (",", "thin"),
+    (":", "med"),
+    (">", "med"),
+    (";", "thick"),
+    (" ", "med"),
+    ("quad", "quad"),
+    ("qquad", "wide"),
+];
+
+/// Operators whose name is typeset upright, which typst knows by the same
+/// name.
+const OPERATORS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "deg", "det", "dim",
+    "exp", "gcd", "hom", "inf", "ker", "lg", "lim", "liminf", "limsup", "ln", "log", "max", "min",
+    "Pr", "sec", "sin", "sinh", "sup", "tan", "tanh",
+];
+
+/// Macros that translate to a function of typst with a single argument.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("mathrm", "upright"),
+    ("mathit", "italic"),
+    ("mathbf", "bold"),
+    ("boldsymbol", "bold"),
+    ("bm", "bold"),
+    ("mathbb", "bb"),
+    ("mathcal", "cal"),
+    ("mathscr", "scr"),
+    ("mathfrak", "frak"),
+    ("mathsf", "sans"),
+    ("mathtt", "mono"),
+    ("hat", "hat"),
+    ("widehat", "hat"),
+    ("tilde", "tilde"),
+    ("widetilde", "tilde"),
+    ("bar", "macron"),
+    ("overline", "overline"),
+    ("underline", "underline"),
+    ("vec", "arrow"),
+    ("overrightarrow", "arrow"),
+    ("dot", "dot"),
+    ("ddot", "dot.double"),
+    ("acute", "acute"),
+    ("grave", "grave"),
+    ("breve", "breve"),
+    ("check", "caron"),
+    ("overbrace", "overbrace"),
+    ("underbrace", "underbrace"),
+    ("cancel", "cancel"),
+];
+
+/// Macros that translate to a function of typst with two arguments.
+const BINARY: &[(&str, &str)] = &[
+    ("frac", "frac"),
+    ("dfrac", "frac"),
+    ("tfrac", "frac"),
+    ("cfrac", "frac"),
+    ("binom", "binom"),
+    ("dbinom", "binom"),
+    ("tbinom", "binom"),
+];
+
+/// Macros that only affect spacing or sizing, which typst takes care of by
+/// itself.
+const IGNORED: &[&str] = &[
+    "limits",
+    "nolimits",
+    "displaystyle",
+    "textstyle",
+    "scriptstyle",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "bigl",
+    "Bigl",
+    "biggl",
+    "Biggl",
+    "bigr",
+    "Bigr",
+    "biggr",
+    "Biggr",
+    "bigm",
+    "Bigm",
+    "label",
+    "tag",
+    "nonumber",
+    "notag",
+    "!",
+];
+
+/// Environments that translate to matrices, along with their delimiter.
+const MATRICES: &[(&str, &str)] = &[
+    ("matrix", "#none"),
+    ("array", "#none"),
+    ("smallmatrix", "#none"),
+    ("pmatrix", "\"(\""),
+    ("bmatrix", "\"[\""),
+    ("Bmatrix", "\"{\""),
+    ("vmatrix", "\"|\""),
+    ("Vmatrix", "\"||\""),
+];
+
+/// Environments that are aligned across lines like typst math itself.
+const ALIGNED: &[&str] = &[
+    "aligned",
+    "align",
+    "align*",
+    "alignat",
+    "alignat*",
+    "gathered",
+    "gather",
+    "gather*",
+    "split",
+    "equation",
+    "equation*",
+    "multline",
+    "multline*",
+];
+
+/// The amount of times macros of the site may be expanded within a single
+/// math span, so that a macro referring to itself is caught.
+const MAX_EXPANSIONS: usize = 1024;
+
+/// LaTeX math translated to typst math.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Translation {
+    /// The typst math.
+    pub typst: String,
+    /// Anything that couldn't be translated.
+    pub errors: Vec<Error>,
+}
+
+/// Something within LaTeX math that couldn't be translated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+    pub hints: Vec<String>,
+    /// Where the error occurred within the LaTeX.
+    pub range: Range<usize>,
+}
+
+/// Translate LaTeX math to typst math, with `macros` defined by the site.
+#[must_use]
+pub fn translate(latex: &str, macros: &BTreeMap<String, String>) -> Translation {
+    let mut translator = Translator {
+        tokens: tokenize(latex, None),
+        position: 0,
+        macros,
+        expansions: 0,
+        arguments: 0,
+        errors: Vec::new(),
+    };
+
+    let typst = translator.sequence(|_| false);
+
+    Translation {
+        typst,
+        errors: translator.errors,
+    }
+}
+
+/// A token of LaTeX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    /// A macro, without its backslash, such as `frac`.
+    Command(&'a str),
+    /// A parameter of a macro of the site, such as `#1`.
+    Parameter(usize),
+    Char(char),
+    Space,
+    Open,
+    Close,
+    Superscript,
+    Subscript,
+    Align,
+}
+
+/// A token, along with where it is within the LaTeX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Spanned<'a> {
+    token: Token<'a>,
+    range: Range<usize>,
+}
+
+/// Split `latex` into tokens, placing all of them at `at` if it's given,
+/// such as for the expansion of a macro.
+fn tokenize<'a>(latex: &'a str, at: Option<&Range<usize>>) -> Vec<Spanned<'a>> {
+    let mut tokens = Vec::new();
+    let mut chars = latex.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            '\\' => match chars.peek().copied() {
+                Some((offset, c)) if c.is_ascii_alphabetic() => {
+                    let mut end = offset;
+
+                    while let Some(&(offset, c)) = chars.peek()
+                        && c.is_ascii_alphabetic()
+                    {
+                        end = offset + c.len_utf8();
+                        chars.next();
+                    }
+
+                    Token::Command(&latex[offset..end])
+                }
+                Some((offset, c)) => {
+                    chars.next();
+                    Token::Command(&latex[offset..offset + c.len_utf8()])
+                }
+                None => Token::Char('\\'),
+            },
+            '#' => match chars.peek().and_then(|&(_, c)| c.to_digit(10)) {
+                Some(digit) => {
+                    chars.next();
+                    Token::Parameter(digit as usize)
+                }
+                None => Token::Char('#'),
+            },
+            '%' => {
+                // NOTE: Comments run until the end of the line.
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                continue;
+            }
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '&' => Token::Align,
+            c if c.is_whitespace() => {
+                while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+                Token::Space
+            }
+            c => Token::Char(c),
+        };
+
+        let end = chars.peek().map_or(latex.len(), |&(offset, _)| offset);
+        let range = at.cloned().unwrap_or(start..end);
+
+        tokens.push(Spanned { token, range });
+    }
+
+    tokens
+}
+
+/// A translator that walks over LaTeX tokens.
+#[derive(Debug)]
+struct Translator<'a> {
+    tokens: Vec<Spanned<'a>>,
+    /// The position of the next token.
+    position: usize,
+    /// The macros defined by the site, keyed by their name.
+    macros: &'a BTreeMap<String, String>,
+    /// The amount of times macros of the site have been expanded.
+    expansions: usize,
+    /// How deeply nested within the arguments of typst functions the
+    /// translation is, where commas and semicolons would separate them.
+    arguments: usize,
+    errors: Vec<Error>,
+}
+
+impl<'a> Translator<'a> {
+    /// Returns the next token without consuming it, skipping spaces.
+    fn peek(&mut self) -> Option<Token<'a>> {
+        while self.tokens.get(self.position)?.token == Token::Space {
+            self.position += 1;
+        }
+
+        Some(self.tokens[self.position].token)
+    }
+
+    /// Consume the next token, skipping spaces.
+    fn next(&mut self) -> Option<Spanned<'a>> {
+        self.peek()?;
+        self.position += 1;
+        self.tokens.get(self.position - 1).cloned()
+    }
+
+    /// The range of the token consumed last.
+    fn last_range(&self) -> Range<usize> {
+        self.position
+            .checked_sub(1)
+            .and_then(|position| self.tokens.get(position))
+            .map_or(0..0, |spanned| spanned.range.clone())
+    }
+
+    /// Report an error at `range`.
+    fn error(&mut self, range: Range<usize>, message: String, hints: Vec<String>) {
+        self.errors.push(Error {
+            message,
+            hints,
+            range,
+        });
+    }
+
+    /// Translate tokens until `stop` returns true for the next one, or there
+    /// aren't any left.
+    fn sequence(&mut self, stop: impl Fn(Token) -> bool) -> String {
+        let mut out = String::new();
+
+        while let Some(token) = self.peek() {
+            if stop(token) {
+                break;
+            }
+
+            self.item(&mut out);
+        }
+
+        out.trim().to_owned()
+    }
+
+    /// Translate tokens until the group they're within is closed.
+    fn group(&mut self) -> String {
+        let range = self.last_range();
+        let out = self.sequence(|token| token == Token::Close);
+
+        if self.next().is_none() {
+            self.error(range, "unclosed group".into(), vec!["add a `}`".into()]);
+        }
+
+        out
+    }
+
+    /// Translate the argument of a macro, which is either a group or a
+    /// single token.
+    fn argument(&mut self) -> String {
+        let Some(token) = self.peek() else {
+            self.error(
+                self.last_range(),
+                "missing argument".into(),
+                vec!["arguments are written within `{` and `}`".into()],
+            );
+
+            return String::new();
+        };
+
+        self.arguments += 1;
+
+        let out = match token {
+            Token::Open => {
+                self.next();
+                self.group()
+            }
+            // NOTE: Only a single character is taken, such as within
+            //       `\frac12`.
+            Token::Char(c) => {
+                self.next();
+                self.char(c)
+            }
+            _ => {
+                let mut out = String::new();
+
+                self.item(&mut out);
+                out.trim().to_owned()
+            }
+        };
+
+        self.arguments -= 1;
+        out
+    }
+
+    /// Returns the argument of a macro as it was written, such as for
+    /// `\text`.
+    fn raw_argument(&mut self) -> String {
+        let mut out = String::new();
+
+        if self.peek() != Some(Token::Open) {
+            if let Some(spanned) = self.next() {
+                raw(spanned.token, &mut out);
+            }
+
+            return out;
+        }
+
+        let range = self.last_range();
+        let mut depth = 0usize;
+
+        self.next();
+
+        loop {
+            let Some(spanned) = self.tokens.get(self.position).cloned() else {
+                self.error(range, "unclosed group".into(), vec!["add a `}`".into()]);
+                break;
+            };
+
+            self.position += 1;
+
+            match spanned.token {
+                Token::Close if depth == 0 => break,
+                Token::Close => depth -= 1,
+                Token::Open => depth += 1,
+                _ => {}
+            }
+
+            raw(spanned.token, &mut out);
+        }
+
+        out
+    }
+
+    /// Translate a single item, such as a letter or a macro along with its
+    /// arguments, appending it to `out`.
+    fn item(&mut self, out: &mut String) {
+        let Some(Spanned { token, range }) = self.next() else {
+            return;
+        };
+
+        match token {
+            Token::Char(c) if c.is_ascii_digit() => {
+                push(out, &c.to_string());
+
+                while let Some(Token::Char(c)) = self.peek()
+                    && (c.is_ascii_digit() || c == '.')
+                {
+                    self.next();
+                    out.push(c);
+                }
+            }
+            Token::Char('\'') => attach(out, '\''),
+            Token::Char(c) => push(out, &self.char(c)),
+            Token::Superscript | Token::Subscript => {
+                let script = self.argument();
+                let script = match atomic(&script) {
+                    true => script,
+                    false => format!("({script})"),
+                };
+
+                attach(
+                    out,
+                    match token {
+                        Token::Superscript => '^',
+                        _ => '_',
+                    },
+                );
+                out.push_str(&script);
+            }
+            Token::Open => {
+                // NOTE: Commas within groups are kept as they are, such as
+                //       within `1{,}5`.
+                self.arguments += 1;
+
+                let group = self.group();
+
+                self.arguments -= 1;
+
+                let attached = matches!(self.peek(), Some(Token::Superscript | Token::Subscript));
+
+                // NOTE: Attachments to a group apply to all of it, which
+                //       parentheses would do as well, but visibly. An empty
+                //       group gives them an empty base, such as within
+                //       `{}^{14}C`, rather than the item before it.
+                match (attached, group.is_empty()) {
+                    (true, true) => push(out, r#""""#),
+                    (true, false) if !atomic(&group) => push(out, &format!("lr({group})")),
+                    _ => push(out, &group),
+                }
+            }
+            Token::Close => self.error(
+                range,
+                "unmatched `}`".into(),
+                vec!["remove it, or add a `{` before it".into()],
+            ),
+            Token::Align => push(out, "&"),
+            Token::Parameter(_) => self.error(
+                range,
+                "parameter outside of a macro".into(),
+                vec!["parameters such as `#1` may only be used by macros of the site".into()],
+            ),
+            Token::Space => {}
+            Token::Command(name) => self.command(name, range, out),
+        }
+    }
+
+    /// Translate a character.
+    fn char(&self, c: char) -> String {
+        match c {
+            // NOTE: LaTeX doesn't make fractions of slashes, unlike typst.
+            '/' | '"' | '$' | '@' | '#' | '`' | '\\' => format!("\\{c}"),
+            ',' | ';' if self.arguments > 0 => format!("\\{c}"),
+            '~' => "space.nobreak".into(),
+            c => c.into(),
+        }
+    }
+
+    /// Translate a macro along with its arguments.
+    fn command(&mut self, name: &'a str, range: Range<usize>, out: &mut String) {
+        let definition = self
+            .macros
+            .get(name)
+            .or_else(|| self.macros.get(&format!("\\{name}")));
+
+        if let Some(definition) = definition {
+            self.expand(name, definition, range);
+            return;
+        }
+
+        if let Some(symbol) = lookup(SYMBOLS, name) {
+            push(out, symbol);
+            return;
+        }
+
+        if OPERATORS.contains(&name) {
+            push(out, name);
+            return;
+        }
+
+        if IGNORED.contains(&name) {
+            // NOTE: The arguments of these are ignored along with them.
+            if matches!(name, "label" | "tag") {
+                self.raw_argument();
+            }
+
+            return;
+        }
+
+        if let Some(function) = lookup(FUNCTIONS, name) {
+            let argument = self.argument();
+
+            push(out, &format!("{function}({argument})"));
+            return;
+        }
+
+        if let Some(function) = lookup(BINARY, name) {
+            let first = self.argument();
+            let second = self.argument();
+
+            push(out, &format!("{function}({first}, {second})"));
+            return;
+        }
+
+        match name {
+            "\\" => {
+                // NOTE: The spacing after a line break is ignored.
+                if self.peek() == Some(Token::Char('[')) {
+                    while self
+                        .next()
+                        .is_some_and(|spanned| spanned.token != Token::Char(']'))
+                    {
+                    }
+                }
+
+                push(out, "\\");
+            }
+            "sqrt" if self.peek() == Some(Token::Char('[')) => {
+                self.next();
+                self.arguments += 1;
+
+                let index = self.sequence(|token| token == Token::Char(']'));
+
+                self.arguments -= 1;
+                self.next();
+
+                let radicand = self.argument();
+
+                push(out, &format!("root({index}, {radicand})"));
+            }
+            "sqrt" => {
+                let radicand = self.argument();
+
+                push(out, &format!("sqrt({radicand})"));
+            }
+            "text" | "textrm" | "textnormal" | "mbox" | "textit" | "textbf" => {
+                let text = self.raw_argument();
+
+                push(out, &string(&text));
+            }
+            "operatorname" => {
+                let limits = self.peek() == Some(Token::Char('*'));
+
+                if limits {
+                    self.next();
+                }
+
+                let text = string(&self.raw_argument());
+
+                match limits {
+                    true => push(out, &format!("op({text}, limits: #true)")),
+                    false => push(out, &format!("op({text})")),
+                }
+            }
+            "pmod" => {
+                let modulus = self.argument();
+
+                push(out, &format!("quad (mod {modulus})"));
+            }
+            "not" => {
+                let negated = match self.next().map(|spanned| spanned.token) {
+                    Some(Token::Char('=')) => Some("eq.not"),
+                    Some(Token::Char('<')) => Some("lt.not"),
+                    Some(Token::Char('>')) => Some("gt.not"),
+                    Some(Token::Command("in")) => Some("in.not"),
+                    Some(Token::Command("subset")) => Some("subset.not"),
+                    Some(Token::Command("subseteq")) => Some("subset.eq.not"),
+                    Some(Token::Command("supset")) => Some("supset.not"),
+                    Some(Token::Command("supseteq")) => Some("supset.eq.not"),
+                    Some(Token::Command("equiv")) => Some("equiv.not"),
+                    Some(Token::Command("exists")) => Some("exists.not"),
+                    _ => None,
+                };
+
+                match negated {
+                    Some(negated) => push(out, negated),
+                    None => self.error(
+                        range.start..self.last_range().end,
+                        "unsupported negation".into(),
+                        vec!["use the negated symbol instead, such as `\\neq`".into()],
+                    ),
+                }
+            }
+            "left" => {
+                let open = self.delimiter();
+
+                self.arguments += 1;
+
+                let inner = self.sequence(|token| token == Token::Command("right"));
+
+                self.arguments -= 1;
+
+                let close = match self.next() {
+                    Some(_) => self.delimiter(),
+                    None => {
+                        self.error(
+                            range,
+                            "`\\left` without `\\right`".into(),
+                            vec!["add a `\\right` after it, such as `\\right.`".into()],
+                        );
+
+                        String::new()
+                    }
+                };
+
+                // NOTE: Typst pairs up parentheses and brackets within `lr`,
+                //       so any that aren't a pair, such as within
+                //       `\left[0, 1\right)`, are escaped.
+                let (open, close) = match (&*open, &*close) {
+                    ("(", ")") | ("[", "]") => (open, close),
+                    _ => (bracket(open), bracket(close)),
+                };
+
+                let body: Vec<&str> = [&*open, &*inner, &*close]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .collect();
+
+                push(out, &format!("lr({})", body.join(" ")));
+            }
+            "middle" => {
+                let delimiter = bracket(self.delimiter());
+
+                push(out, &format!("mid({delimiter})"));
+            }
+            "right" => self.error(
+                range,
+                "`\\right` without `\\left`".into(),
+                vec!["add a `\\left` before it, such as `\\left.`".into()],
+            ),
+            "begin" => self.environment(range, out),
+            "end" => {
+                let name = self.raw_argument();
+
+                self.error(
+                    range,
+                    format!("`\\end{{{name}}}` without `\\begin{{{name}}}`"),
+                    Vec::new(),
+                );
+            }
+            _ => {
+                self.error(
+                    range,
+                    format!("unsupported LaTeX macro `\\{name}`"),
+                    vec![format!(
+                        "define it under `[latex_macros]` within `site.toml`, such as `{name} = \"...\"`"
+                    )],
+                );
+
+                push(out, &string(&format!("\\{name}")));
+            }
+        }
+    }
+
+    /// Translate a delimiter, such as after `\left`.
+    fn delimiter(&mut self) -> String {
+        match self.next().map(|spanned| spanned.token) {
+            Some(Token::Char('.')) | None => String::new(),
+            Some(Token::Char(c)) => self.char(c),
+            Some(Token::Command(name)) => match lookup(SYMBOLS, name) {
+                Some(symbol) => symbol.into(),
+                None => {
+                    self.error(
+                        self.last_range(),
+                        format!("unsupported delimiter `\\{name}`"),
+                        Vec::new(),
+                    );
+
+                    String::new()
+                }
+            },
+            Some(_) => {
+                self.error(self.last_range(), "missing delimiter".into(), Vec::new());
+                String::new()
+            }
+        }
+    }
+
+    /// Translate an environment, such as a matrix.
+    fn environment(&mut self, range: Range<usize>, out: &mut String) {
+        let name = self.raw_argument();
+
+        // NOTE: The column specification of an array is of no use to typst.
+        if name == "array" || name.starts_with("alignat") {
+            self.raw_argument();
+        }
+
+        let matrix = lookup(MATRICES, &name);
+        let cases = name == "cases";
+
+        if matrix.is_some() || cases {
+            self.arguments += 1;
+        }
+
+        let mut rows: Vec<Vec<String>> = vec![Vec::new()];
+
+        loop {
+            let cell = self.sequence(|token| {
+                matches!(
+                    token,
+                    Token::Align | Token::Command("\\") | Token::Command("end")
+                )
+            });
+
+            rows.last_mut().unwrap().push(cell);
+
+            match self.next().map(|spanned| spanned.token) {
+                Some(Token::Align) => {}
+                Some(Token::Command("\\")) => {
+                    if self.peek() == Some(Token::Char('[')) {
+                        while self
+                            .next()
+                            .is_some_and(|spanned| spanned.token != Token::Char(']'))
+                        {
+                        }
+                    }
+
+                    rows.push(Vec::new());
+                }
+                Some(_) => {
+                    let end = self.raw_argument();
+
+                    if end != name {
+                        self.error(
+                            self.last_range(),
+                            format!("`\\begin{{{name}}}` ended by `\\end{{{end}}}`"),
+                            Vec::new(),
+                        );
+                    }
+
+                    break;
+                }
+                None => {
+                    self.error(
+                        range.clone(),
+                        format!("`\\begin{{{name}}}` without `\\end{{{name}}}`"),
+                        vec![format!("add a `\\end{{{name}}}` after it")],
+                    );
+
+                    break;
+                }
+            }
+        }
+
+        if matrix.is_some() || cases {
+            self.arguments -= 1;
+        }
+
+        // NOTE: A line break after the last row doesn't start another one.
+        if rows.len() > 1
+            && rows
+                .last()
+                .is_some_and(|row| row.iter().all(String::is_empty))
+        {
+            rows.pop();
+        }
+
+        if let Some(delim) = matrix {
+            let rows: Vec<_> = rows.iter().map(|row| row.join(", ")).collect();
+
+            push(out, &format!("mat(delim: {delim}, {})", rows.join("; ")));
+        } else if cases {
+            let rows: Vec<_> = rows.iter().map(|row| row.join(" & ")).collect();
+
+            push(out, &format!("cases({})", rows.join(", ")));
+        } else if ALIGNED.contains(&name.as_str()) {
+            let rows: Vec<_> = rows.iter().map(|row| row.join(" & ")).collect();
+
+            push(out, &rows.join(" \\\n"));
+        } else {
+            self.error(
+                range,
+                format!("unsupported LaTeX environment `{name}`"),
+                Vec::new(),
+            );
+        }
+    }
+
+    /// Expand a macro of the site in place, substituting its parameters with
+    /// the arguments that follow it.
+    fn expand(&mut self, name: &str, definition: &'a str, range: Range<usize>) {
+        self.expansions += 1;
+
+        if self.expansions > MAX_EXPANSIONS {
+            // NOTE: Only report this once, rather than for every expansion
+            //       that follows.
+            if self.expansions == MAX_EXPANSIONS + 1 {
+                self.error(
+                    range,
+                    format!("macro `\\{name}` expanded too many times"),
+                    vec!["macros that refer to themselves are never done expanding".into()],
+                );
+            }
+
+            return;
+        }
+
+        let body = tokenize(definition, Some(&range));
+        let parameters = body
+            .iter()
+            .filter_map(|spanned| match spanned.token {
+                Token::Parameter(parameter) => Some(parameter),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut arguments = Vec::with_capacity(parameters);
+
+        for _ in 0..parameters {
+            arguments.push(self.raw_tokens());
+        }
+
+        let expansion: Vec<Spanned> = body
+            .into_iter()
+            .flat_map(|spanned| match spanned.token {
+                Token::Parameter(parameter) => arguments
+                    .get(parameter.wrapping_sub(1))
+                    .cloned()
+                    .unwrap_or_default(),
+                _ => vec![spanned],
+            })
+            .collect();
+
+        self.tokens.splice(self.position..self.position, expansion);
+    }
+
+    /// Consume the tokens of an argument as they are, within braces if it's a
+    /// group, such as for the argument of a macro of the site.
+    fn raw_tokens(&mut self) -> Vec<Spanned<'a>> {
+        let Some(token) = self.peek() else {
+            return Vec::new();
+        };
+
+        let start = self.position;
+
+        if token != Token::Open {
+            self.position += 1;
+            return self.tokens[start..self.position].to_vec();
+        }
+
+        let mut depth = 0usize;
+
+        while let Some(spanned) = self.tokens.get(self.position) {
+            self.position += 1;
+
+            match spanned.token {
+                Token::Open => depth += 1,
+                Token::Close if depth == 1 => break,
+                Token::Close => depth -= 1,
+                _ => {}
+            }
+        }
+
+        self.tokens[start..self.position].to_vec()
+    }
+}
+
+/// Append a token of LaTeX as it was written.
+fn raw(token: Token, out: &mut String) {
+    match token {
+        Token::Command(name) => {
+            out.push('\\');
+            out.push_str(name);
+        }
+        Token::Parameter(parameter) => {
+            out.push('#');
+            out.push_str(&parameter.to_string());
+        }
+        Token::Char(c) => out.push(c),
+        Token::Space => out.push(' '),
+        Token::Open => out.push('{'),
+        Token::Close => out.push('}'),
+        Token::Superscript => out.push('^'),
+        Token::Subscript => out.push('_'),
+        Token::Align => out.push('&'),
+    }
+}
+
+/// Append an item of typst math, separated from the one before it.
+///
+/// Items are always separated, as letters next to each other would be taken
+/// as a single identifier by typst, and symbols as a shorthand.
+fn push(out: &mut String, item: &str) {
+    if !out.is_empty() && !out.ends_with(' ') {
+        out.push(' ');
+    }
+
+    out.push_str(item);
+}
+
+/// Append an attachment marker, right after the item it attaches to.
+///
+/// Attachments without an item before them, such as at the start of a group
+/// or a cell, are given an empty base, as typst would take the marker as is.
+fn attach(out: &mut String, marker: char) {
+    out.truncate(out.trim_end().len());
+
+    if out.is_empty() || out.ends_with(['(', '&']) {
+        push(out, r#""""#);
+    }
+
+    out.push(marker);
+}
+
+/// Escape `delimiter` if it's a parenthesis or a bracket, which typst would
+/// otherwise pair up with another.
+fn bracket(delimiter: String) -> String {
+    match &*delimiter {
+        "(" | ")" | "[" | "]" => format!("\\{delimiter}"),
+        _ => delimiter,
+    }
+}
+
+/// Returns whether `typst` is a single item, which needn't be wrapped in
+/// parentheses to be attached.
+fn atomic(typst: &str) -> bool {
+    let mut chars = typst.chars();
+
+    match (chars.next(), chars.next()) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        _ => typst.chars().all(|c| c.is_alphanumeric() || c == '.'),
+    }
+}
+
+/// Convert text into a typst string literal.
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+
+    out.push('"');
+
+    for c in text.chars() {
+        if matches!(c, '"' | '\\') {
+            out.push('\\');
+        }
+
+        out.push(c);
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, ops::Range};
+
+    use super::{Error, Translation, translate};
+
+    /// Translate `latex` without any macros, expecting it to translate
+    /// cleanly.
+    fn typst(latex: &str) -> String {
+        let Translation { typst, errors } = translate(latex, &BTreeMap::new());
+
+        assert_eq!(errors, Vec::new(), "errors translating {latex:?}");
+        typst
+    }
+
+    /// Translate `latex` without any macros, returning the messages and
+    /// ranges of its errors.
+    fn errors(latex: &str) -> Vec<(String, Range<usize>)> {
+        translate(latex, &BTreeMap::new())
+            .errors
+            .into_iter()
+            .map(|Error { message, range, .. }| (message, range))
+            .collect()
+    }
+
+    #[test]
+    fn frac() {
+        assert_eq!(typst(r"\frac{1}{2}"), "frac(1, 2)");
+        assert_eq!(typst(r"\frac12"), "frac(1, 2)");
+        assert_eq!(typst(r"\frac{a+b}{c}"), "frac(a + b, c)");
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(typst("x^2"), "x^2");
+        assert_eq!(typst("x_i^2"), "x_i^2");
+        assert_eq!(typst("x^{10}"), "x^10");
+        assert_eq!(typst(r"e^{i\pi}"), "e^(i pi)");
+        assert_eq!(typst("x_{i,j}"), r"x_(i \, j)");
+        assert_eq!(typst(r"\sum_{i=1}^n i"), "sum_(i = 1)^n i");
+        assert_eq!(typst("f'"), "f'");
+    }
+
+    #[test]
+    fn scripts_without_base() {
+        assert_eq!(typst("{}^{14}C"), r#"""^14 C"#);
+        assert_eq!(typst("^2"), r#"""^2"#);
+    }
+
+    #[test]
+    fn environments() {
+        assert_eq!(
+            typst(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+            r#"mat(delim: "(", a, b; c, d)"#
+        );
+        assert_eq!(
+            typst(r"\begin{cases} 1 & x > 0 \\ 0 & \text{otherwise} \end{cases}"),
+            r#"cases(1 & x > 0, 0 & "otherwise")"#
+        );
+        assert_eq!(
+            typst(r"\begin{aligned} a &= b \\ c &= d \end{aligned}"),
+            "a & = b \\\nc & = d"
+        );
+    }
+
+    #[test]
+    fn macros() {
+        let macros = BTreeMap::from([
+            ("R".to_owned(), r"\mathbb{R}".to_owned()),
+            ("norm".to_owned(), r"\left\| #1 \right\|".to_owned()),
+        ]);
+
+        assert_eq!(translate(r"x \in \R", &macros).typst, "x in bb(R)");
+        assert_eq!(translate(r"\norm{x}", &macros).typst, "lr(|| x ||)");
+    }
+
+    #[test]
+    fn recursive_macros() {
+        let macros = BTreeMap::from([("loop".to_owned(), r"\loop".to_owned())]);
+        let Translation { errors, .. } = translate(r"\loop", &macros);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].range, 0..5);
+    }
+
+    #[test]
+    fn error_ranges() {
+        assert_eq!(
+            errors(r"x + \foo"),
+            [("unsupported LaTeX macro `\\foo`".to_owned(), 4..8)]
+        );
+        assert_eq!(
+            errors(r"\frac{1}{\foo}"),
+            [("unsupported LaTeX macro `\\foo`".to_owned(), 9..13)]
+        );
+        assert_eq!(errors("{a"), [("unclosed group".to_owned(), 0..1)]);
+        assert_eq!(errors("a}"), [("unmatched `}`".to_owned(), 1..2)]);
+        assert_eq!(
+            errors(r"\begin{pmatrix} a"),
+            [(
+                "`\\begin{pmatrix}` without `\\end{pmatrix}`".to_owned(),
+                0..6
+            )]
+        );
+    }
+
+    #[test]
+    fn left_right() {
+        assert_eq!(typst(r"\left( x \right)"), "lr(( x ))");
+        assert_eq!(typst(r"\left[ x \right]"), "lr([ x ])");
+    }
+
+    #[test]
+    fn left_right_unmatched() {
+        assert_eq!(typst(r"\left[0,1\right)"), r"lr(\[ 0 \, 1 \))");
+        assert_eq!(typst(r"\left( x \right."), r"lr(\( x)");
+        assert_eq!(typst(r"\left. x \right|"), "lr(x |)");
+    }
+}
@@ -0,0 +1,943 @@
+//! Conversion of typst math to presentation MathML.
+//!
+//! Only the syntax of typst math is understood, such as attachments,
+//! fractions, roots and delimiters, along with the most common symbols and
+//! functions. Code within math isn't evaluated, so anything beyond that is
+//! kept as text, and typst remains the reference for how math looks.
+
+use typst::syntax::{SyntaxKind, SyntaxNode, parse_math};
+
+use crate::{content::math::lookup, util};
+
+/// Symbols by their name in typst, including any modifiers.
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("epsilon.alt", "ϵ"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("theta.alt", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("kappa.alt", "ϰ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("omicron", "ο"),
+    ("pi", "π"),
+    ("pi.alt", "ϖ"),
+    ("rho", "ρ"),
+    ("rho.alt", "ϱ"),
+    ("sigma", "σ"),
+    ("sigma.alt", "ς"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("phi.alt", "ϕ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Alpha", "Α"),
+    ("Beta", "Β"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Epsilon", "Ε"),
+    ("Zeta", "Ζ"),
+    ("Eta", "Η"),
+    ("Theta", "Θ"),
+    ("Iota", "Ι"),
+    ("Kappa", "Κ"),
+    ("Lambda", "Λ"),
+    ("Mu", "Μ"),
+    ("Nu", "Ν"),
+    ("Xi", "Ξ"),
+    ("Omicron", "Ο"),
+    ("Pi", "Π"),
+    ("Rho", "Ρ"),
+    ("Sigma", "Σ"),
+    ("Tau", "Τ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Chi", "Χ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("plus", "+"),
+    ("plus.minus", "±"),
+    ("plus.circle", "⊕"),
+    ("minus", "−"),
+    ("minus.plus", "∓"),
+    ("times", "×"),
+    ("times.circle", "⊗"),
+    ("div", "÷"),
+    ("dot", "⋅"),
+    ("dot.op", "⋅"),
+    ("dot.c", "·"),
+    ("ast", "∗"),
+    ("star", "⋆"),
+    ("compose", "∘"),
+    ("without", "∖"),
+    ("divides", "∣"),
+    ("eq", "="),
+    ("eq.not", "≠"),
+    ("lt", "<"),
+    ("lt.eq", "≤"),
+    ("lt.double", "≪"),
+    ("gt", ">"),
+    ("gt.eq", "≥"),
+    ("gt.double", "≫"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("prop", "∝"),
+    ("tilde.op", "∼"),
+    ("colon.eq", "≔"),
+    ("in", "∈"),
+    ("in.not", "∉"),
+    ("in.rev", "∋"),
+    ("subset", "⊂"),
+    ("subset.eq", "⊆"),
+    ("supset", "⊃"),
+    ("supset.eq", "⊇"),
+    ("union", "∪"),
+    ("union.big", "⋃"),
+    ("inter", "∩"),
+    ("inter.big", "⋂"),
+    ("emptyset", "∅"),
+    ("infinity", "∞"),
+    ("oo", "∞"),
+    ("nabla", "∇"),
+    ("diff", "∂"),
+    ("partial", "∂"),
+    ("sum", "∑"),
+    ("product", "∏"),
+    ("product.co", "∐"),
+    ("integral", "∫"),
+    ("integral.double", "∬"),
+    ("integral.triple", "∭"),
+    ("integral.cont", "∮"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("exists.not", "∄"),
+    ("not", "¬"),
+    ("and", "∧"),
+    ("and.big", "⋀"),
+    ("or", "∨"),
+    ("or.big", "⋁"),
+    ("arrow.r", "→"),
+    ("arrow.r.long", "⟶"),
+    ("arrow.r.double", "⇒"),
+    ("arrow.r.double.long", "⟹"),
+    ("arrow.r.bar", "↦"),
+    ("arrow.r.hook", "↪"),
+    ("arrow.r.squiggly", "⇝"),
+    ("arrow.l", "←"),
+    ("arrow.l.long", "⟵"),
+    ("arrow.l.double", "⇐"),
+    ("arrow.l.double.long", "⟸"),
+    ("arrow.l.r", "↔"),
+    ("arrow.l.r.double", "⇔"),
+    ("arrow.l.r.double.long", "⟺"),
+    ("arrow.t", "↑"),
+    ("arrow.b", "↓"),
+    ("arrow.t.b", "↕"),
+    ("dots.h", "…"),
+    ("dots.h.c", "⋯"),
+    ("dots.c", "⋯"),
+    ("dots.v", "⋮"),
+    ("dots.down", "⋱"),
+    ("prime", "′"),
+    ("degree", "°"),
+    ("angle", "∠"),
+    ("perp", "⟂"),
+    ("parallel", "∥"),
+    ("bot", "⊥"),
+    ("top", "⊤"),
+    ("tack.r", "⊢"),
+    ("models", "⊨"),
+    ("aleph", "ℵ"),
+    ("ell", "ℓ"),
+    ("planck.reduce", "ℏ"),
+    ("hbar", "ℏ"),
+    ("Re", "ℜ"),
+    ("Im", "ℑ"),
+    ("NN", "ℕ"),
+    ("ZZ", "ℤ"),
+    ("QQ", "ℚ"),
+    ("RR", "ℝ"),
+    ("CC", "ℂ"),
+    ("qed", "∎"),
+];
+
+/// Shorthands by what they're written as.
+const SHORTHANDS: &[(&str, &str)] = &[
+    ("-", "−"),
+    ("*", "∗"),
+    ("'", "′"),
+    ("~", "∼"),
+    ("...", "…"),
+    ("->", "→"),
+    ("-->", "⟶"),
+    ("=>", "⇒"),
+    ("==>", "⟹"),
+    ("<-", "←"),
+    ("<--", "⟵"),
+    ("<==", "⟸"),
+    ("<->", "↔"),
+    ("<=>", "⇔"),
+    ("<==>", "⟺"),
+    ("|->", "↦"),
+    ("~>", "⇝"),
+    ("!=", "≠"),
+    ("<=", "≤"),
+    (">=", "≥"),
+    ("<<", "≪"),
+    (">>", "≫"),
+    (":=", "≔"),
+    ("||", "‖"),
+    ("[|", "⟦"),
+    ("|]", "⟧"),
+];
+
+/// Spacing by its name in typst, as a width in `em`.
+const SPACES: &[(&str, &str)] = &[
+    ("thin", "0.1667em"),
+    ("med", "0.2222em"),
+    ("thick", "0.2778em"),
+    ("quad", "1em"),
+    ("wide", "2em"),
+];
+
+/// Functions whose name is typeset upright, such as `sin`.
+const OPERATORS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "csch", "deg", "det",
+    "dim", "exp", "gcd", "lcm", "hom", "id", "im", "inf", "ker", "lg", "lim", "liminf", "limsup",
+    "ln", "log", "max", "min", "mod", "Pr", "sec", "sech", "sin", "sinc", "sinh", "sup", "tan",
+    "tanh", "tg", "tr",
+];
+
+/// Operators whose attachments are placed above and below them within
+/// display math, such as `sum`.
+const LIMITS: &[&str] = &[
+    "sum",
+    "product",
+    "product.co",
+    "union.big",
+    "inter.big",
+    "and.big",
+    "or.big",
+    "lim",
+    "liminf",
+    "limsup",
+    "max",
+    "min",
+    "sup",
+    "inf",
+];
+
+/// Accents by the name of their function in typst.
+const ACCENTS: &[(&str, &str)] = &[
+    ("hat", "^"),
+    ("tilde", "~"),
+    ("macron", "¯"),
+    ("overline", "¯"),
+    ("dot", "˙"),
+    ("dot.double", "¨"),
+    ("acute", "´"),
+    ("grave", "`"),
+    ("breve", "˘"),
+    ("caron", "ˇ"),
+    ("arrow", "→"),
+];
+
+/// Delimiters of functions that wrap their argument, such as `abs`.
+const WRAPPERS: &[(&str, &str, &str)] = &[
+    ("abs", "|", "|"),
+    ("norm", "‖", "‖"),
+    ("floor", "⌊", "⌋"),
+    ("ceil", "⌈", "⌉"),
+    ("round", "⌊", "⌉"),
+];
+
+/// The style of letters and digits, as set by functions such as `bold`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Upright,
+    Italic,
+    Bold,
+    DoubleStruck,
+    Script,
+    Fraktur,
+    SansSerif,
+    Monospace,
+}
+
+impl Variant {
+    /// Returns the variant set by the function `name`, if any.
+    fn from_function(name: &str) -> Option<Variant> {
+        Some(match name {
+            "upright" => Variant::Upright,
+            "italic" => Variant::Italic,
+            "bold" => Variant::Bold,
+            "bb" => Variant::DoubleStruck,
+            "cal" | "scr" => Variant::Script,
+            "frak" => Variant::Fraktur,
+            "sans" => Variant::SansSerif,
+            "mono" => Variant::Monospace,
+            _ => return None,
+        })
+    }
+
+    /// Style `c` with the variant, with its mathematical alphanumeric
+    /// symbol, if there is one.
+    fn style(self, c: char) -> char {
+        // NOTE: Some letters were encoded before the rest, and are found
+        //       elsewhere as a result.
+        let exception = match (self, c) {
+            (Variant::Italic, 'h') => Some('ℎ'),
+            (Variant::DoubleStruck, 'C') => Some('ℂ'),
+            (Variant::DoubleStruck, 'H') => Some('ℍ'),
+            (Variant::DoubleStruck, 'N') => Some('ℕ'),
+            (Variant::DoubleStruck, 'P') => Some('ℙ'),
+            (Variant::DoubleStruck, 'Q') => Some('ℚ'),
+            (Variant::DoubleStruck, 'R') => Some('ℝ'),
+            (Variant::DoubleStruck, 'Z') => Some('ℤ'),
+            (Variant::Script, 'B') => Some('ℬ'),
+            (Variant::Script, 'E') => Some('ℰ'),
+            (Variant::Script, 'F') => Some('ℱ'),
+            (Variant::Script, 'H') => Some('ℋ'),
+            (Variant::Script, 'I') => Some('ℐ'),
+            (Variant::Script, 'L') => Some('ℒ'),
+            (Variant::Script, 'M') => Some('ℳ'),
+            (Variant::Script, 'R') => Some('ℛ'),
+            (Variant::Script, 'e') => Some('ℯ'),
+            (Variant::Script, 'g') => Some('ℊ'),
+            (Variant::Script, 'o') => Some('ℴ'),
+            (Variant::Fraktur, 'C') => Some('ℭ'),
+            (Variant::Fraktur, 'H') => Some('ℌ'),
+            (Variant::Fraktur, 'I') => Some('ℑ'),
+            (Variant::Fraktur, 'R') => Some('ℜ'),
+            (Variant::Fraktur, 'Z') => Some('ℨ'),
+            _ => None,
+        };
+
+        if let Some(exception) = exception {
+            return exception;
+        }
+
+        let (upper, lower, digits) = match self {
+            Variant::Upright => return c,
+            Variant::Italic => (0x1D434, 0x1D44E, None),
+            Variant::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+            Variant::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8)),
+            Variant::Script => (0x1D49C, 0x1D4B6, None),
+            Variant::Fraktur => (0x1D504, 0x1D51E, None),
+            Variant::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2)),
+            Variant::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+        };
+
+        let styled = match c {
+            'A'..='Z' => upper + (c as u32 - 'A' as u32),
+            'a'..='z' => lower + (c as u32 - 'a' as u32),
+            '0'..='9' if let Some(digits) = digits => digits + (c as u32 - '0' as u32),
+            _ => return c,
+        };
+
+        char::from_u32(styled).unwrap_or(c)
+    }
+}
+
+/// Convert typst math to a MathML `math` element, keeping the source as an
+/// annotation so that it may still be copied.
+#[must_use]
+pub fn convert(math: &str, display: bool) -> String {
+    let root = parse_math(math);
+    let mut converter = Converter {
+        out: String::new(),
+        display,
+        variant: None,
+    };
+
+    converter.out.push_str(match display {
+        true => r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="block">"#,
+        false => r#"<math xmlns="http://www.w3.org/1998/Math/MathML">"#,
+    });
+    converter.out.push_str("<semantics>");
+    converter.sequence(root.children());
+    converter
+        .out
+        .push_str(r#"<annotation encoding="application/x-typst">"#);
+    util::escape(math, &mut converter.out);
+    converter.out.push_str("</annotation></semantics></math>");
+    converter.out
+}
+
+/// A converter that walks over the syntax tree of typst math.
+#[derive(Debug)]
+struct Converter {
+    /// The MathML written so far.
+    out: String,
+    /// Whether the math is displayed as a block.
+    display: bool,
+    /// The style of letters and digits within the current function, if any.
+    variant: Option<Variant>,
+}
+
+impl Converter {
+    /// Convert a sequence of nodes to a single row, or to a table if it's
+    /// broken into lines or aligned.
+    fn sequence<'n>(&mut self, nodes: impl IntoIterator<Item = &'n SyntaxNode>) {
+        let nodes: Vec<&SyntaxNode> = nodes.into_iter().collect();
+        let aligned = nodes.iter().any(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::Linebreak | SyntaxKind::MathAlignPoint
+            )
+        });
+
+        if !aligned {
+            self.out.push_str("<mrow>");
+
+            for node in nodes {
+                self.node(node);
+            }
+
+            self.out.push_str("</mrow>");
+            return;
+        }
+
+        let rows: Vec<_> = nodes
+            .split(|node| node.kind() == SyntaxKind::Linebreak)
+            .collect();
+        let columns = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .filter(|node| node.kind() == SyntaxKind::MathAlignPoint)
+                    .count()
+                    + 1
+            })
+            .max()
+            .unwrap_or(1);
+
+        // NOTE: Like typst, columns are alternately aligned to the right and
+        //       the left of their alignment points.
+        let align: Vec<_> = (0..columns)
+            .map(|column| match column % 2 {
+                0 => "right",
+                _ => "left",
+            })
+            .collect();
+
+        self.out.push_str(r#"<mtable columnalign=""#);
+        self.out.push_str(&align.join(" "));
+        self.out.push_str(r#"">"#);
+
+        for row in rows {
+            self.out.push_str("<mtr>");
+
+            for cell in row.split(|node| node.kind() == SyntaxKind::MathAlignPoint) {
+                self.out.push_str("<mtd>");
+                self.sequence(cell.iter().copied());
+                self.out.push_str("</mtd>");
+            }
+
+            self.out.push_str("</mtr>");
+        }
+
+        self.out.push_str("</mtable>");
+    }
+
+    /// Convert a node to a single element, without any parentheses around
+    /// it, as is done for the operands of fractions and attachments.
+    fn operand(&mut self, node: &SyntaxNode) {
+        let children: Vec<_> = node.children().collect();
+
+        match children.as_slice() {
+            [open, inner @ .., close]
+                if node.kind() == SyntaxKind::MathDelimited
+                    && open.text().as_str() == "("
+                    && close.text().as_str() == ")" =>
+            {
+                self.sequence(inner.iter().copied());
+            }
+            _ => self.sequence([node]),
+        }
+    }
+
+    /// Convert a single node.
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::Math => self.sequence(node.children()),
+            SyntaxKind::MathText | SyntaxKind::Text => self.text(node.text().as_str()),
+            SyntaxKind::MathIdent => self.identifier(node.text().as_str()),
+            SyntaxKind::FieldAccess => self.identifier(&node.clone().into_text()),
+            SyntaxKind::MathShorthand => {
+                let text = node.text().as_str();
+                let symbol = lookup(SHORTHANDS, text).unwrap_or(text);
+
+                self.element("mo", symbol);
+            }
+            SyntaxKind::Escape => {
+                let text = node.text().as_str();
+
+                self.element("mo", text.strip_prefix('\\').unwrap_or(text));
+            }
+            SyntaxKind::Str => {
+                let text = node.text().as_str();
+                let text = text
+                    .strip_prefix('"')
+                    .and_then(|text| text.strip_suffix('"'))
+                    .unwrap_or(text);
+
+                self.element("mtext", text);
+            }
+            SyntaxKind::MathPrimes => self.element("mo", &"′".repeat(primes(node))),
+            SyntaxKind::MathDelimited => {
+                let children: Vec<_> = node.children().collect();
+
+                self.out.push_str("<mrow>");
+
+                if let [open, inner @ .., close] = children.as_slice() {
+                    self.fence(open.text().as_str());
+                    self.sequence(inner.iter().copied());
+                    self.fence(close.text().as_str());
+                }
+
+                self.out.push_str("</mrow>");
+            }
+            SyntaxKind::MathAttach => self.attach(node),
+            SyntaxKind::MathFrac => {
+                let mut operands = node
+                    .children()
+                    .filter(|child| child.kind() != SyntaxKind::Slash);
+
+                self.out.push_str("<mfrac>");
+
+                for operand in operands.by_ref().take(2) {
+                    self.operand(operand);
+                }
+
+                self.out.push_str("</mfrac>");
+            }
+            SyntaxKind::MathRoot => {
+                let mut children = node.children();
+                let index = match children.next().map(|root| root.text().as_str()) {
+                    Some("∛") => Some("3"),
+                    Some("∜") => Some("4"),
+                    _ => None,
+                };
+
+                let Some(radicand) = children.next() else {
+                    return;
+                };
+
+                match index {
+                    Some(index) => {
+                        self.out.push_str("<mroot>");
+                        self.operand(radicand);
+                        self.element("mn", index);
+                        self.out.push_str("</mroot>");
+                    }
+                    None => {
+                        self.out.push_str("<msqrt>");
+                        self.operand(radicand);
+                        self.out.push_str("</msqrt>");
+                    }
+                }
+            }
+            SyntaxKind::FuncCall => self.call(node),
+            SyntaxKind::Space
+            | SyntaxKind::Linebreak
+            | SyntaxKind::MathAlignPoint
+            | SyntaxKind::Dollar => {}
+            // NOTE: Anything else, such as embedded code, can't be converted
+            //       without evaluating it.
+            _ => self.element("mtext", &node.clone().into_text()),
+        }
+    }
+
+    /// Convert a run of text, such as a number or a single letter.
+    fn text(&mut self, text: &str) {
+        let mut chars = text.chars();
+
+        if text.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            self.element("mn", text);
+        } else if let (Some(c), None) = (chars.next(), chars.next()) {
+            match c.is_alphabetic() {
+                true => self.element("mi", text),
+                false => self.element("mo", text),
+            }
+        } else {
+            self.element("mtext", text);
+        }
+    }
+
+    /// Convert an identifier, such as a symbol or the name of an operator.
+    fn identifier(&mut self, name: &str) {
+        if let Some(width) = lookup(SPACES, name) {
+            self.out.push_str(r#"<mspace width=""#);
+            self.out.push_str(width);
+            self.out.push_str(r#""/>"#);
+        } else if let Some(symbol) = lookup(SYMBOLS, name) {
+            match symbol.chars().all(char::is_alphabetic) {
+                true => self.element("mi", symbol),
+                false => self.element("mo", symbol),
+            }
+        } else {
+            self.element("mi", name);
+        }
+    }
+
+    /// Convert an attachment, with its base and any subscript, superscript
+    /// and primes.
+    fn attach(&mut self, node: &SyntaxNode) {
+        let mut children = node.children();
+        let Some(base) = children.next() else {
+            return;
+        };
+
+        let mut sub = None;
+        let mut sup = None;
+        let mut primes = 0;
+
+        while let Some(child) = children.next() {
+            match child.kind() {
+                SyntaxKind::Underscore => sub = children.next(),
+                SyntaxKind::Hat => sup = children.next(),
+                SyntaxKind::MathPrimes => primes = self::primes(child),
+                _ => {}
+            }
+        }
+
+        let limits = self.display
+            && matches!(base.kind(), SyntaxKind::MathIdent | SyntaxKind::FieldAccess)
+            && LIMITS.contains(&base.clone().into_text().as_str());
+
+        let tag = match (sub.is_some(), sup.is_some() || primes > 0, limits) {
+            (true, true, true) => "munderover",
+            (true, false, true) => "munder",
+            (false, true, true) => "mover",
+            (true, true, false) => "msubsup",
+            (true, false, false) => "msub",
+            (false, true, false) => "msup",
+            (false, false, _) => {
+                self.node(base);
+                return;
+            }
+        };
+
+        self.out.push('<');
+        self.out.push_str(tag);
+        self.out.push('>');
+        self.sequence([base]);
+
+        if let Some(sub) = sub {
+            self.operand(sub);
+        }
+
+        if sup.is_some() || primes > 0 {
+            self.out.push_str("<mrow>");
+
+            if primes > 0 {
+                self.element("mo", &"′".repeat(primes));
+            }
+
+            if let Some(sup) = sup {
+                self.operand(sup);
+            }
+
+            self.out.push_str("</mrow>");
+        }
+
+        self.out.push_str("</");
+        self.out.push_str(tag);
+        self.out.push('>');
+    }
+
+    /// Convert a function call, such as `frac(a, b)` or `vec(x, y)`.
+    fn call(&mut self, node: &SyntaxNode) {
+        let mut children = node.children();
+        let (Some(callee), Some(args)) = (children.next(), children.next()) else {
+            return;
+        };
+
+        let name = callee.clone().into_text();
+        let rows = arguments(args);
+        let positional: Vec<&SyntaxNode> = rows.iter().flatten().copied().collect();
+
+        match (name.as_str(), positional.as_slice()) {
+            ("frac", [numerator, denominator]) => {
+                self.out.push_str("<mfrac>");
+                self.operand(numerator);
+                self.operand(denominator);
+                self.out.push_str("</mfrac>");
+            }
+            ("binom", [upper, lower]) => {
+                self.out.push_str("<mrow>");
+                self.fence("(");
+                self.out.push_str(r#"<mfrac linethickness="0">"#);
+                self.operand(upper);
+                self.operand(lower);
+                self.out.push_str("</mfrac>");
+                self.fence(")");
+                self.out.push_str("</mrow>");
+            }
+            ("sqrt", [radicand]) => {
+                self.out.push_str("<msqrt>");
+                self.operand(radicand);
+                self.out.push_str("</msqrt>");
+            }
+            ("root", [index, radicand]) => {
+                self.out.push_str("<mroot>");
+                self.operand(radicand);
+                self.operand(index);
+                self.out.push_str("</mroot>");
+            }
+            ("vec", _) => self.matrix("(", ")", positional.iter().map(std::slice::from_ref)),
+            ("mat", _) => self.matrix("(", ")", rows.iter().map(Vec::as_slice)),
+            ("cases", _) => {
+                self.out.push_str("<mrow>");
+                self.fence("{");
+                self.table("left", positional.iter().map(std::slice::from_ref));
+                self.out.push_str("</mrow>");
+            }
+            ("op", [text]) => self.element("mi", &(*text).clone().into_text().replace('"', "")),
+            ("lr" | "mid", _) => self.sequence(positional),
+            ("underline", [base]) => {
+                self.out.push_str(r#"<munder accentunder="true">"#);
+                self.operand(base);
+                self.element("mo", "_");
+                self.out.push_str("</munder>");
+            }
+            (name, [inner]) if let Some(variant) = Variant::from_function(name) => {
+                let outer = self.variant.replace(variant);
+
+                self.operand(inner);
+                self.variant = outer;
+            }
+            (name, [base]) if let Some(accent) = lookup(ACCENTS, name) => {
+                self.out.push_str(r#"<mover accent="true">"#);
+                self.operand(base);
+                self.element("mo", accent);
+                self.out.push_str("</mover>");
+            }
+            (name, [inner]) if let Some((_, open, close)) = wrapper(name) => {
+                self.out.push_str("<mrow>");
+                self.fence(open);
+                self.operand(inner);
+                self.fence(close);
+                self.out.push_str("</mrow>");
+            }
+            // NOTE: Anything else is typeset like typst does when the callee
+            //       isn't a function, with its arguments in parentheses.
+            _ => {
+                self.out.push_str("<mrow>");
+
+                match OPERATORS.contains(&name.as_str()) {
+                    true => self.element("mi", &name),
+                    false => self.identifier(&name),
+                }
+
+                self.fence("(");
+
+                for (index, argument) in positional.iter().enumerate() {
+                    if index > 0 {
+                        self.element("mo", ",");
+                    }
+
+                    self.sequence([*argument]);
+                }
+
+                self.fence(")");
+                self.out.push_str("</mrow>");
+            }
+        }
+    }
+
+    /// Convert rows of cells to a table within delimiters.
+    fn matrix<'n, 'r>(
+        &mut self,
+        open: &str,
+        close: &str,
+        rows: impl IntoIterator<Item = &'r [&'n SyntaxNode]>,
+    ) where
+        'n: 'r,
+    {
+        self.out.push_str("<mrow>");
+        self.fence(open);
+        self.table("center", rows);
+        self.fence(close);
+        self.out.push_str("</mrow>");
+    }
+
+    /// Convert rows of cells to a table.
+    fn table<'n, 'r>(&mut self, align: &str, rows: impl IntoIterator<Item = &'r [&'n SyntaxNode]>)
+    where
+        'n: 'r,
+    {
+        self.out.push_str(r#"<mtable columnalign=""#);
+        self.out.push_str(align);
+        self.out.push_str(r#"">"#);
+
+        for row in rows {
+            self.out.push_str("<mtr>");
+
+            for cell in row {
+                self.out.push_str("<mtd>");
+
+                match cell.kind() {
+                    SyntaxKind::Math => self.sequence(
+                        cell.children()
+                            .filter(|child| child.kind() != SyntaxKind::MathAlignPoint),
+                    ),
+                    _ => self.sequence([*cell]),
+                }
+
+                self.out.push_str("</mtd>");
+            }
+
+            self.out.push_str("</mtr>");
+        }
+
+        self.out.push_str("</mtable>");
+    }
+
+    /// Write a stretchy delimiter.
+    fn fence(&mut self, delimiter: &str) {
+        self.out.push_str(r#"<mo fence="true">"#);
+        util::escape(delimiter, &mut self.out);
+        self.out.push_str("</mo>");
+    }
+
+    /// Write an element containing `text`, styled with the current variant.
+    fn element(&mut self, tag: &str, text: &str) {
+        self.out.push('<');
+        self.out.push_str(tag);
+
+        // NOTE: Single letters are italic by default, which would otherwise
+        //       undo setting them upright.
+        if self.variant == Some(Variant::Upright) && tag == "mi" {
+            self.out.push_str(r#" mathvariant="normal""#);
+        }
+
+        self.out.push('>');
+
+        match self.variant {
+            Some(variant) if tag != "mo" => {
+                let styled: String = text.chars().map(|c| variant.style(c)).collect();
+
+                util::escape(&styled, &mut self.out);
+            }
+            _ => util::escape(text, &mut self.out),
+        }
+
+        self.out.push_str("</");
+        self.out.push_str(tag);
+        self.out.push('>');
+    }
+}
+
+/// Returns the positional arguments of a function call, split into rows by
+/// semicolons.
+fn arguments(args: &SyntaxNode) -> Vec<Vec<&SyntaxNode>> {
+    let mut rows = vec![Vec::new()];
+
+    for child in args.children() {
+        match child.kind() {
+            SyntaxKind::Semicolon => rows.push(Vec::new()),
+            // NOTE: Named arguments, such as `delim`, are ignored.
+            SyntaxKind::LeftParen
+            | SyntaxKind::RightParen
+            | SyntaxKind::Comma
+            | SyntaxKind::Space
+            | SyntaxKind::Named => {}
+            _ => rows.last_mut().unwrap().push(child),
+        }
+    }
+
+    rows.retain(|row| !row.is_empty());
+    rows
+}
+
+/// Returns the amount of primes within `node`.
+fn primes(node: &SyntaxNode) -> usize {
+    node.children()
+        .filter(|child| child.kind() == SyntaxKind::Prime)
+        .count()
+        .max(1)
+}
+
+/// Returns the delimiters of a wrapping function.
+fn wrapper(name: &str) -> Option<(&str, &str, &str)> {
+    WRAPPERS
+        .iter()
+        .copied()
+        .find(|&(wrapper, ..)| wrapper == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert;
+
+    #[test]
+    fn display() {
+        assert!(
+            convert("x", true).starts_with(
+                r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="block">"#
+            )
+        );
+        assert!(
+            convert("x", false).starts_with(r#"<math xmlns="http://www.w3.org/1998/Math/MathML">"#)
+        );
+    }
+
+    #[test]
+    fn annotation() {
+        assert!(convert("a < b", false).ends_with(
+            r#"<annotation encoding="application/x-typst">a &lt; b</annotation></semantics></math>"#
+        ));
+    }
+
+    #[test]
+    fn frac() {
+        let expected = "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>";
+
+        assert!(convert("a/b", false).contains(expected));
+        assert!(convert("frac(a, b)", false).contains(expected));
+    }
+
+    #[test]
+    fn scripts() {
+        let mathml = convert("x^2", false);
+
+        assert!(mathml.contains("<msup>"));
+        assert!(mathml.contains("<mn>2</mn>"));
+        assert!(convert("x_i^2", false).contains("<msubsup>"));
+    }
+
+    #[test]
+    fn limits() {
+        assert!(convert("sum_(i=1)^n i", true).contains("<munderover>"));
+        assert!(convert("sum_(i=1)^n i", false).contains("<msubsup>"));
+    }
+
+    #[test]
+    fn symbols() {
+        assert!(convert("alpha + beta", false).contains("<mi>α</mi>"));
+        assert!(convert("sqrt(x)", false).contains("<msqrt>"));
+        assert!(convert("upright(d) x", false).contains(r#"<mi mathvariant="normal">"#));
+    }
+
+    #[test]
+    fn matrices() {
+        let mathml = convert("vec(1, 2)", false);
+
+        assert!(mathml.contains(r#"<mtable columnalign="center">"#));
+        assert!(mathml.contains("<mn>1</mn>"));
+        assert!(mathml.contains("<mn>2</mn>"));
+    }
+}
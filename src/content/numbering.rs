@@ -0,0 +1,370 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    query::Changed,
+    system::{Commands, Query, Res, ResMut},
+};
+use jotdown::{AttributeKind, Attributes, Container, Event};
+use typst::diag::Severity;
+
+use crate::{
+    content::{
+        Input, diagram,
+        djot::{self, DjotEvents, DjotOffsets, ids},
+        page::PageOffset,
+    },
+    site::{Site, Theorems},
+    typst::diagnostic::{Diagnostic, Diagnostics, Origin},
+};
+
+/// The kinds of things that are numbered within a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    Figure,
+    Listing,
+    Table,
+    Equation,
+    Theorem,
+    Lemma,
+    Definition,
+}
+
+impl Kind {
+    /// The name of this kind, as shown in captions and references.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Figure => "Figure",
+            Kind::Listing => "Listing",
+            Kind::Table => "Table",
+            Kind::Equation => "Equation",
+            Kind::Theorem => "Theorem",
+            Kind::Lemma => "Lemma",
+            Kind::Definition => "Definition",
+        }
+    }
+
+    /// The class given to the elements of this kind.
+    #[must_use]
+    pub fn class(self) -> &'static str {
+        match self {
+            Kind::Figure => "figure",
+            Kind::Listing => "listing",
+            Kind::Table => "table",
+            Kind::Equation => "equation",
+            Kind::Theorem => "theorem",
+            Kind::Lemma => "lemma",
+            Kind::Definition => "definition",
+        }
+    }
+
+    /// Returns the kind of theorem made from divs with `class`, if any.
+    #[must_use]
+    pub fn theorem(class: &str) -> Option<Kind> {
+        match class {
+            "theorem" => Some(Kind::Theorem),
+            "lemma" => Some(Kind::Lemma),
+            "definition" => Some(Kind::Definition),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this is a kind of theorem.
+    #[must_use]
+    pub fn is_theorem(self) -> bool {
+        matches!(self, Kind::Theorem | Kind::Lemma | Kind::Definition)
+    }
+
+    /// The kind whose counter this kind is numbered by.
+    #[must_use]
+    pub fn counter(self, theorems: Theorems) -> Kind {
+        match theorems {
+            Theorems::Shared if self.is_theorem() => Kind::Theorem,
+            _ => self,
+        }
+    }
+}
+
+/// The number given to something within a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Label {
+    pub kind: Kind,
+    pub number: usize,
+}
+
+impl Label {
+    /// The text references to this are rendered as, such as `Figure 1`.
+    #[must_use]
+    pub fn reference(self) -> String {
+        match self.kind {
+            Kind::Equation => format!("({})", self.number),
+            kind => format!("{} {}", kind.name(), self.number),
+        }
+    }
+}
+
+/// The labels of everything numbered within a page.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+pub struct Labels {
+    /// The labels keyed by the position of the event that starts what they
+    /// label.
+    pub positions: BTreeMap<usize, Label>,
+    /// The labels keyed by the id of what they label.
+    pub ids: HashMap<String, Label>,
+}
+
+impl Labels {
+    /// Number everything within `events`.
+    ///
+    /// Numbered are:
+    ///
+    /// - `::: figure` divs, which are listings if they contain code, or tables
+    ///   if they contain a table.
+    /// - Paragraphs containing nothing but an image, if either has the
+    ///   `figure` class or an id, such as `![A cat](cat.png){.figure}`.
+    /// - Tables with a caption.
+    /// - Display math with an id, such as ``$$`E = m c^2`{#eq-energy}``.
+    /// - `::: theorem`, `::: lemma` and `::: definition` divs, which share a
+    ///   counter if `theorems` is [`Theorems::Shared`].
+    #[must_use]
+    pub fn new(events: &[Event<'static>], theorems: Theorems) -> Labels {
+        let mut labels = Labels::default();
+        let mut counters: HashMap<Kind, usize> = HashMap::new();
+        let mut position = 0;
+
+        while let Some(event) = events.get(position) {
+            let Event::Start(container, attributes) = event else {
+                position += 1;
+                continue;
+            };
+
+            let inner = || &events[position + 1..djot::end(events, position)];
+
+            let kind = match container {
+                Container::Div { class: "figure" } => Some(figure_kind(inner())),
+                Container::Div { class } => Kind::theorem(class),
+                Container::Paragraph if image_figure(events, position) => Some(Kind::Figure),
+                Container::Table if inner().iter().any(is_caption) => Some(Kind::Table),
+                Container::Math { display: true } if ids::explicit_id(attributes).is_some() => {
+                    Some(Kind::Equation)
+                }
+                _ => None,
+            };
+
+            let Some(kind) = kind else {
+                position += 1;
+                continue;
+            };
+
+            let counter = counters.entry(kind.counter(theorems)).or_default();
+            *counter += 1;
+
+            let label = Label {
+                kind,
+                number: *counter,
+            };
+
+            labels.positions.insert(position, label);
+
+            let id = ids::explicit_id(attributes).or_else(|| match &events[position + 1] {
+                Event::Start(Container::Image(..), attributes) if kind == Kind::Figure => {
+                    ids::explicit_id(attributes)
+                }
+                _ => None,
+            });
+
+            if let Some(id) = id {
+                labels.ids.insert(id, label);
+            }
+
+            // NOTE: Anything within a figure, such as the table of a table
+            //       figure, is part of it rather than being numbered itself.
+            position = match container {
+                Container::Div { class: "figure" } => djot::end(events, position) + 1,
+                _ => position + 1,
+            };
+        }
+
+        labels
+    }
+
+    /// Returns the label of what starts at `position`.
+    #[inline]
+    #[must_use]
+    pub fn at(&self, position: usize) -> Option<Label> {
+        self.positions.get(&position).copied()
+    }
+
+    /// Returns the label of what has the id `id`.
+    #[inline]
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<Label> {
+        self.ids.get(id).copied()
+    }
+}
+
+/// Returns the kind of a figure from its contents.
+fn figure_kind(events: &[Event<'static>]) -> Kind {
+    // NOTE: Diagrams are drawn from code, but they're figures rather than
+    //       listings.
+    let is_listing = |event: &Event<'static>| match event {
+        Event::Start(container @ Container::CodeBlock { .. }, attributes) => {
+            !diagram::is_diagram(container, attributes)
+        }
+        _ => false,
+    };
+
+    let is_table = |event: &Event<'static>| matches!(event, Event::Start(Container::Table, _));
+
+    if events.iter().any(is_listing) {
+        Kind::Listing
+    } else if events.iter().any(is_table) {
+        Kind::Table
+    } else {
+        Kind::Figure
+    }
+}
+
+fn is_caption(event: &Event<'static>) -> bool {
+    matches!(event, Event::Start(Container::Caption, _))
+}
+
+/// Returns whether the paragraph starting at `position` contains nothing but
+/// an image.
+#[must_use]
+pub fn lone_image(events: &[Event<'static>], position: usize) -> bool {
+    let Some(Event::Start(Container::Image(..), _)) = events.get(position + 1) else {
+        return false;
+    };
+
+    let end = djot::end(events, position + 1);
+
+    matches!(events.get(end + 1), Some(Event::End(Container::Paragraph)))
+}
+
+/// Returns whether the paragraph starting at `position` is a figure, that is
+/// a lone image that's opted into being numbered with the `figure` class or an
+/// id, on either the paragraph or the image.
+///
+/// Other images are left alone, as they're often decorative.
+#[must_use]
+pub fn image_figure(events: &[Event<'static>], position: usize) -> bool {
+    if !lone_image(events, position) {
+        return false;
+    }
+
+    let opted_in = |attributes: &Attributes<'static>| {
+        attributes.iter().any(|(kind, value)| match kind {
+            AttributeKind::Class => value.parts().collect::<String>() == "figure",
+            AttributeKind::Id => true,
+            _ => false,
+        })
+    };
+
+    [position, position + 1]
+        .into_iter()
+        .any(|position| match &events[position] {
+            Event::Start(_, attributes) => opted_in(attributes),
+            _ => false,
+        })
+}
+
+/// Returns the id a cross-reference starting at `position` refers to.
+///
+/// Cross-references are links to `#id` without any text, such as `[](#fig-foo)`.
+#[must_use]
+pub fn reference<'a>(events: &'a [Event<'static>], position: usize) -> Option<&'a str> {
+    let Some(Event::Start(Container::Link(url, _), _)) = events.get(position) else {
+        return None;
+    };
+
+    let Some(Event::End(Container::Link(..))) = events.get(position + 1) else {
+        return None;
+    };
+
+    url.strip_prefix('#')
+}
+
+/// Number everything within each page, reporting an error for each
+/// cross-reference that doesn't refer to anything.
+///
+/// References to headings, sections, or anything else with an id are left as
+/// the links they are.
+pub fn number(
+    query: Query<
+        (
+            Entity,
+            &DjotEvents,
+            &DjotOffsets,
+            &Input<Path>,
+            &Input<String>,
+            &PageOffset,
+        ),
+        Changed<DjotEvents>,
+    >,
+    site: Res<Site>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut commands: Commands,
+) {
+    for (
+        entity,
+        DjotEvents(events),
+        DjotOffsets(offsets),
+        Input(path),
+        Input(contents),
+        &PageOffset(offset),
+    ) in query
+    {
+        let labels = Labels::new(events, site.theorems);
+        let targets = targets(events);
+
+        let origin = Origin {
+            path,
+            contents,
+            start: 0,
+            offset,
+        };
+
+        for position in 0..events.len() {
+            let Some(id) = reference(events, position) else {
+                continue;
+            };
+
+            if labels.get(id).is_some() || targets.contains(id) {
+                continue;
+            }
+
+            diagnostics.push(Diagnostic::at(
+                Severity::Error,
+                format!("unresolved reference to {id:?}"),
+                vec!["add the id to what it refers to, such as `{#fig-foo}`".into()],
+                offsets.get(position).cloned(),
+                origin,
+            ));
+        }
+
+        commands.entity(entity).insert(labels);
+    }
+}
+
+/// Returns every id within `events` that links may refer to, such as those
+/// of headings and sections.
+fn targets(events: &[Event<'static>]) -> HashSet<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Start(Container::Heading { id, .. } | Container::Section { id }, _)
+                if !id.is_empty() =>
+            {
+                Some(id.to_string())
+            }
+            Event::Start(_, attributes) => ids::explicit_id(attributes),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use anyhow::Context as _;
+use bevy::ecs::{
+    error::Result,
+    query::{Changed, Or},
+    system::{Query, Res},
+};
+
+use crate::{content::Output, site::Site};
+
+/// Write every changed output to the output directory.
+///
+/// Outputs are either text, with [`Output<String>`], or binary, with
+/// [`Output<[u8]>`], such as exported PDFs.
+pub fn write(
+    query: Query<
+        (
+            &Output<Path>,
+            Option<&Output<String>>,
+            Option<&Output<[u8]>>,
+        ),
+        Or<(
+            Changed<Output<Path>>,
+            Changed<Output<String>>,
+            Changed<Output<[u8]>>,
+        )>,
+    >,
+    site: Res<Site>,
+) -> Result<()> {
+    for (Output(path), text, binary) in query {
+        let contents: &[u8] = match (text, binary) {
+            (Some(Output(text)), _) => text.as_bytes(),
+            (None, Some(Output(binary))) => binary,
+            (None, None) => continue,
+        };
+
+        let path = site.output.join(path);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {:?}", parent.display()))?;
+        }
+
+        std::fs::write(&path, contents).with_context(|| format!("writing {:?}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     ffi::OsStr,
     path::{Path, PathBuf},
 };
@@ -8,19 +9,37 @@ use bevy::ecs::{
     component::Component,
     entity::Entity,
     error::Result,
-    query::{Changed, With},
-    system::{Commands, Query},
+    query::{Changed, Has, Or, With, Without},
+    system::{Commands, EntityCommands, Query, Res},
 };
 
+use chrono::{DateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     bevy::EntityCommandsExt as _,
-    content::{Input, front_matter::FrontMatter},
+    content::{
+        Input, InputModified, Output,
+        collection::{Collection, CollectionConfig},
+        diagram::Preamble,
+        djot::Footnotes,
+        feed,
+        front_matter::FrontMatter,
+        head::{Head, OgType},
+        history::{History, Revisions},
+        layout::{Context, Layouts, PageLayout},
+        math::{MathOutput, MathSyntax},
+        pdf::{self, Pdf, Pdfs},
+        route::Route,
+        taxonomy::Terms,
+        toc::{TableOfContents, Toc},
+        typ::{Export, TypstPage},
+    },
+    site::Site,
 };
 
 /// A struct for storing the metadata for pages.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
 pub struct PageMatter {
     /// The title of the page.
     #[serde(default)]
@@ -37,12 +56,51 @@ pub struct PageMatter {
     /// Whether the page is considered a draft.
     #[serde(default)]
     pub draft: bool,
+    /// Whether search engines should be asked not to index the page.
+    #[serde(default)]
+    pub noindex: bool,
     /// When the page was originally created.
     #[serde(default)]
     pub created: Option<chrono::NaiveDate>,
     /// When the page was last modified.
     #[serde(default)]
     pub modified: Option<chrono::NaiveDate>,
+    /// The name of the layout to render the page with.
+    #[serde(default)]
+    pub layout: Option<String>,
+    /// Whether to show a table of contents, or the deepest heading level to
+    /// include in it.
+    #[serde(default)]
+    pub toc: Toc,
+    /// How to render footnotes.
+    #[serde(default)]
+    pub footnotes: Footnotes,
+    /// The typst shared by every diagram within the page.
+    #[serde(default)]
+    pub preamble: Option<String>,
+    /// How the page is exported, if it's written in typst.
+    #[serde(default)]
+    pub export: Export,
+    /// Whether to publish the revision history of the page.
+    #[serde(default)]
+    pub history: bool,
+    /// Whether to export the page to PDF.
+    #[serde(default)]
+    pub pdf: bool,
+    /// The syntax math is written in.
+    #[serde(default)]
+    pub math: Option<MathSyntax>,
+    /// How math is rendered, if not as the site says.
+    #[serde(default)]
+    pub math_output: Option<MathOutput>,
+    /// The terms of additional taxonomies, such as `series`, keyed by the
+    /// name of the taxonomy.
+    #[serde(default)]
+    pub taxonomies: BTreeMap<String, Vec<String>>,
+    /// Any other fields, which are passed along to typst, see
+    /// [`Inputs`](crate::typst::inputs::Inputs).
+    #[serde(default)]
+    pub extra: toml::Table,
 }
 
 /// A title component.
@@ -57,6 +115,10 @@ pub struct Description(pub String);
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Slug(pub String);
 
+/// The fields of the `[extra]` table of the front matter of a page.
+#[derive(Debug, Clone, PartialEq, Default, Component)]
+pub struct Extra(pub toml::Table);
+
 /// Keyword list component.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Keywords(pub Vec<String>);
@@ -65,18 +127,44 @@ pub struct Keywords(pub Vec<String>);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Draft;
 
+/// No index component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct NoIndex;
+
 /// Created time component.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Created(pub chrono::NaiveDate);
 
+impl Created {
+    /// Returns the start of the day this was created, in UTC.
+    #[inline]
+    #[must_use]
+    pub fn to_utc(self) -> DateTime<Utc> {
+        self.0.and_time(NaiveTime::MIN).and_utc()
+    }
+}
+
 /// Created time component.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Modified(pub chrono::NaiveDate);
 
+impl Modified {
+    /// Returns the start of the day this was modified, in UTC.
+    #[inline]
+    #[must_use]
+    pub fn to_utc(self) -> DateTime<Utc> {
+        self.0.and_time(NaiveTime::MIN).and_utc()
+    }
+}
+
 /// Marker component for pages.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct Page;
 
+/// The rendered body of a page.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Body(pub String);
+
 /// Page content offset component.
 ///
 /// This details how many bytes from the start of the input contents of a page,
@@ -84,7 +172,16 @@ pub struct Page;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
 pub struct PageOffset(pub usize);
 
-pub fn find(mut commands: Commands) -> Result<()> {
+/// Returns whether `path` is a page, either written in djot or typst.
+fn is_page(path: &Path) -> bool {
+    matches!(path.extension().and_then(OsStr::to_str), Some("dj" | "typ"))
+}
+
+/// Find every page within the site.
+///
+/// The print template of the site is written in typst, but isn't a page, see
+/// [`Site::print_template`].
+pub fn find(site: Res<Site>, mut commands: Commands) -> Result<()> {
     fn find_pages_inner(root: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
         let mut read_dir =
             std::fs::read_dir(root).with_context(|| format!("reading {:?}", root.display()))?;
@@ -95,9 +192,14 @@ pub fn find(mut commands: Commands) -> Result<()> {
                 .file_type()
                 .with_context(|| format!("getting metadata for {:?}", root.display()))?;
 
+            // NOTE: Files starting with `_` are reserved for things such as
+            //       `_index.dj`, which configure collections, and typst
+            //       modules imported by pages.
+            let reserved = entry.file_name().as_encoded_bytes().starts_with(b"_");
+
             if file_type.is_dir() {
                 find_pages_inner(&*path, paths)?;
-            } else if file_type.is_file() && path.extension() == Some(OsStr::new("dj")) {
+            } else if file_type.is_file() && !reserved && is_page(&path) {
                 paths.push(path);
             }
         }
@@ -109,11 +211,22 @@ pub fn find(mut commands: Commands) -> Result<()> {
 
     find_pages_inner(Path::new("."), &mut file_paths)?;
 
-    commands.spawn_batch(
-        file_paths
-            .into_iter()
-            .map(|path| (Page, Input::<Path>(path.into()))),
-    );
+    let template = site
+        .print_template
+        .as_deref()
+        .map(|template| template.strip_prefix(".").unwrap_or(template));
+
+    for path in file_paths {
+        if Some(path.strip_prefix(".").unwrap_or(&path)) == template {
+            continue;
+        }
+
+        let typst = path.extension() == Some(OsStr::new("typ"));
+
+        commands
+            .spawn((Page, Input::<Path>(path.into())))
+            .modify((typst, TypstPage));
+    }
 
     Ok(())
 }
@@ -126,62 +239,209 @@ pub fn read(
         let contents = std::fs::read_to_string(path)
             .with_context(|| format!("reading {:?}", path.display()))?;
 
-        commands.entity(entity).insert(Input::<String>(contents));
+        commands
+            .entity(entity)
+            .insert(Input::<String>(contents))
+            .modify(InputModified::of(path));
     }
 
     Ok(())
 }
 
-pub fn load_matter(
-    query: Query<(Entity, &Input<String>, &Input<Path>), (With<Page>, Changed<Input<String>>)>,
-    mut commands: Commands,
-) -> Result<()> {
-    for (entity, Input(contents), Input(path)) in query {
-        let front_matter = FrontMatter::parse(contents).ok();
-        let page_offset = match &front_matter {
-            Some(front_matter) => contents.len() - front_matter.rest.len(),
-            None => 0,
-        };
+impl PageMatter {
+    /// Parse the front matter of the page at `path`, if it has any.
+    pub fn parse(front_matter: Option<&FrontMatter>, path: &Path) -> Result<PageMatter> {
+        match front_matter {
+            None => Ok(PageMatter::default()),
+            Some(FrontMatter {
+                language: Some("toml") | None,
+                content,
+                ..
+            }) => Ok(toml::from_str(content)
+                .with_context(|| format!("parsing {:?} as toml", path.display()))?),
+            Some(FrontMatter {
+                language: Some(language),
+                ..
+            }) => Err(anyhow!("unknown language, {language:?} in {:?}", path.display()).into()),
+        }
+    }
 
+    /// Insert the components described by this matter into `entity`.
+    pub fn insert(self, entity: &mut EntityCommands) {
         let PageMatter {
             title,
             description,
             slug,
             keywords,
             draft,
+            noindex,
             created,
             modified,
-        } = match &front_matter {
-            None => Default::default(),
-            Some(FrontMatter {
-                language: Some("toml") | None,
-                content,
-                ..
-            }) => toml::from_str(content)
-                .with_context(|| format!("parsing {:?} as toml", path.display()))?,
-            Some(FrontMatter {
-                language: Some(language),
-                ..
-            }) => {
-                return Err(
-                    anyhow!("unknown language, {language:?} in {:?}", path.display()).into(),
-                );
-            }
-        };
+            layout,
+            toc,
+            footnotes,
+            preamble,
+            export: _,
+            history,
+            pdf,
+            math,
+            math_output,
+            taxonomies,
+            extra,
+        } = self;
 
-        commands
-            .entity(entity)
+        entity
             .insert((
                 Title(title),
                 Description(description),
                 Keywords(keywords),
-                PageOffset(page_offset),
+                Terms(taxonomies),
+                toc,
+                footnotes,
+                Extra(extra),
             ))
             .modify((draft, Draft))
+            .modify((noindex, NoIndex))
+            .modify((history, History))
+            .modify((pdf, Pdf))
+            .modify(math)
+            .modify(math_output)
             .modify(slug.map(Slug))
+            .modify(layout.map(PageLayout))
+            .modify(preamble.map(Preamble))
             .modify(created.map(Created))
             .modify(modified.map(Modified));
     }
+}
+
+pub fn load_matter(
+    query: Query<
+        (Entity, &Input<String>, &Input<Path>),
+        (With<Page>, Without<TypstPage>, Changed<Input<String>>),
+    >,
+    mut commands: Commands,
+) -> Result<()> {
+    for (entity, Input(contents), Input(path)) in query {
+        let front_matter = FrontMatter::parse(contents).ok();
+        let page_offset = match &front_matter {
+            Some(front_matter) => contents.len() - front_matter.rest.len(),
+            None => 0,
+        };
+
+        let matter = PageMatter::parse(front_matter.as_ref(), path)?;
+        let mut entity = commands.entity(entity);
+
+        entity.insert(PageOffset(page_offset));
+        matter.insert(&mut entity);
+    }
+
+    Ok(())
+}
+
+/// Render every page with its layout, falling back to the default layout of its
+/// collection.
+pub fn render(
+    query: Query<
+        (
+            Entity,
+            &Body,
+            &Title,
+            &Description,
+            &Keywords,
+            &Route,
+            Option<&Created>,
+            Option<&Modified>,
+            Option<&TableOfContents>,
+            Option<&PageLayout>,
+            Option<&Collection>,
+            Has<NoIndex>,
+            Has<Pdfs>,
+            Has<Revisions>,
+            &Input<Path>,
+        ),
+        (
+            With<Page>,
+            Or<(
+                Changed<Body>,
+                Changed<TableOfContents>,
+                Changed<PageLayout>,
+                Changed<Revisions>,
+            )>,
+        ),
+    >,
+    collections: Query<&CollectionConfig>,
+    layouts: Res<Layouts>,
+    site: Res<Site>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (
+        entity,
+        Body(body),
+        Title(title),
+        Description(description),
+        Keywords(keywords),
+        route,
+        created,
+        modified,
+        toc,
+        page_layout,
+        collection,
+        noindex,
+        has_pdf,
+        has_history,
+        Input(path),
+    ) in query
+    {
+        let name = match page_layout {
+            Some(PageLayout(name)) => name.as_str(),
+            None => collection
+                .and_then(|Collection(collection)| collections.get(*collection).ok())
+                .and_then(|config| config.layout.as_deref())
+                .unwrap_or(Layouts::DEFAULT),
+        };
+
+        let layout = layouts
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown layout, {name:?} in {:?}", path.display()))?;
+
+        let head = Head {
+            title: title.clone(),
+            description: description.clone(),
+            url: route.url(&site.url),
+            og_type: OgType::Article {
+                published: created.map(|created| created.to_utc()),
+                modified: modified.map(|modified| modified.to_utc()),
+                expiration: None,
+                section: None,
+                author: Vec::new(),
+                tags: keywords.clone(),
+            },
+            links: feed::links(&site.title, &Route::root()),
+            robots: noindex.then(|| "noindex".into()),
+        };
+
+        let pdf = has_pdf.then(|| pdf::link(route));
+        let history = has_history.then(|| route.join("history").0);
+        let document = layout.render(&Context {
+            site: &site,
+            head: &head,
+            title,
+            description,
+            keywords,
+            route,
+            created: created.map(|created| created.0),
+            modified: modified.map(|modified| modified.0),
+            toc,
+            pdf: pdf.as_deref(),
+            history: history.as_deref(),
+            body,
+        });
+
+        commands.entity(entity).insert((
+            Output::<Path>(route.file().into()),
+            Output::<String>(document.into_string()),
+        ));
+    }
 
     Ok(())
 }
@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    error::Result,
+    lifecycle::RemovedComponents,
+    query::{Changed, Or, With},
+    system::{Commands, Query, Res, ResMut},
+};
+use chrono::Datelike as _;
+use typst::diag::Warned;
+use typst_pdf::PdfOptions;
+
+use crate::{
+    content::{
+        Input, Output,
+        diagram::Preamble,
+        djot::{
+            DjotEvents,
+            to_typst::{self, string},
+        },
+        math::{Latex, Math, MathOf, MathPosition},
+        numbering::Labels,
+        page::{Created, Title},
+        route::Route,
+    },
+    site::Site,
+    typst::{
+        diagnostic::{Diagnostics, Origin},
+        inputs::Inputs,
+        world::{Compiled, TypstWorld},
+    },
+};
+
+/// Marker component for pages that are exported to PDF, as set by the `pdf`
+/// key in their front matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Pdf;
+
+/// Relates a PDF to the page it was exported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+#[relationship(relationship_target = Pdfs)]
+pub struct PdfOf(pub Entity);
+
+/// The PDFs exported from a page.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+#[relationship_target(relationship = PdfOf, linked_spawn)]
+pub struct Pdfs(Vec<Entity>);
+
+/// The name of the PDF exported from a page, within its route.
+pub const FILE: &str = "post.pdf";
+
+/// The print template used when the site doesn't configure one.
+///
+/// A print template is a typst file defining a `template` function, which is
+/// applied to the body of every page with a show rule.
+pub const TEMPLATE: &str = r#"#let template(title: "", author: "", date: none, body) = {
+  set document(title: title, author: author)
+  set page(paper: "a4", margin: 2.5cm, numbering: "1")
+  set text(size: 11pt)
+  set par(justify: true)
+  set heading(numbering: "1.1")
+  set math.equation(numbering: "(1)")
+
+  align(center)[
+    #text(size: 20pt, weight: "bold", title)
+
+    #author
+    #if date != none [ · #date.display("[year]-[month]-[day]")]
+  ]
+
+  body
+}
+"#;
+
+/// Returns the route of the PDF exported from the page at `route`.
+#[must_use]
+pub fn link(route: &Route) -> String {
+    format!("{}{FILE}", route.0)
+}
+
+/// Export every page with [`Pdf`] to PDF, by transpiling it to typst and
+/// compiling it with the print template of the site.
+///
+/// The files a PDF depends on are kept even when it fails to compile, so that
+/// it's exported again once they're fixed. The PDFs of pages that are no
+/// longer exported are removed.
+pub fn generate(
+    query: Query<
+        (
+            Entity,
+            &DjotEvents,
+            &Labels,
+            &Title,
+            &Route,
+            Option<&Created>,
+            &Input<Path>,
+            &Input<String>,
+            &Inputs,
+            Option<&Preamble>,
+        ),
+        (
+            With<Pdf>,
+            Or<(
+                Changed<DjotEvents>,
+                Changed<Labels>,
+                Changed<Inputs>,
+                Changed<Preamble>,
+            )>,
+        ),
+    >,
+    spans: Query<(&MathOf, &MathPosition, &Math), With<Latex>>,
+    mut removed: RemovedComponents<Pdf>,
+    site: Res<Site>,
+    world: Res<TypstWorld>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut commands: Commands,
+) -> Result<()> {
+    for page in removed.read() {
+        if let Ok(mut page) = commands.get_entity(page) {
+            page.despawn_related::<Pdfs>();
+        }
+    }
+
+    let mut math: HashMap<Entity, HashMap<usize, &str>> = HashMap::new();
+
+    for (&MathOf(page), &MathPosition(position), span) in &spans {
+        let (Math::Inline(typst) | Math::Block(typst)) = span;
+
+        math.entry(page).or_default().insert(position, typst);
+    }
+
+    let none = HashMap::new();
+    let template = match &site.print_template {
+        Some(path) => {
+            // NOTE: Make sure the template exists up front, so that a typo is
+            //       reported as such, rather than as a typst error per page.
+            std::fs::metadata(world.root().join(path))
+                .with_context(|| format!("reading print template {:?}", path.display()))?;
+
+            let path = Path::new("/").join(path);
+
+            format!("#import {}: template\n", string(&path.to_string_lossy()))
+        }
+        None => TEMPLATE.to_owned(),
+    };
+
+    for (
+        page,
+        DjotEvents(events),
+        labels,
+        Title(title),
+        route,
+        created,
+        Input(path),
+        Input(contents),
+        inputs,
+        preamble,
+    ) in query
+    {
+        commands.entity(page).despawn_related::<Pdfs>();
+
+        let date = match created {
+            Some(Created(date)) => format!(
+                "datetime(year: {}, month: {}, day: {})",
+                date.year(),
+                date.month(),
+                date.day()
+            ),
+            None => "none".into(),
+        };
+
+        let mut source = template.clone();
+
+        source.push_str(&format!(
+            "#show: template.with(title: {}, author: {}, date: {date})\n\n",
+            string(title),
+            string(&site.author.name)
+        ));
+
+        // NOTE: Diagrams rely on the preamble of the page, such as for the
+        //       packages they import.
+        if let Some(Preamble(preamble)) = preamble {
+            source.push_str(preamble);
+            source.push_str("\n\n");
+        }
+
+        source.push_str(&to_typst::transpile(
+            events,
+            to_typst::Options {
+                site: &site,
+                labels,
+                math: math.get(&page).unwrap_or(&none),
+            },
+        ));
+
+        // NOTE: The source is placed next to the page, so that images are
+        //       resolved relative to it like they are on the site.
+        let main = TypstWorld::file_source(&path.with_extension("pdf.typ"), source);
+        let Compiled {
+            warned: Warned { output, warnings },
+            dependencies,
+        } = world.compile(&main, inputs);
+
+        // NOTE: The source is transpiled, so diagnostics can't be mapped back
+        //       to the page, and are attributed to it as a whole instead.
+        let origin = Origin {
+            path,
+            contents,
+            start: usize::MAX,
+            offset: 0,
+        };
+
+        diagnostics.report(&warnings, &main, origin);
+
+        let pdf = output.and_then(|document| typst_pdf::pdf(&document, &PdfOptions::default()));
+
+        let mut exported = commands.spawn((PdfOf(page), dependencies));
+
+        match pdf {
+            Ok(pdf) => {
+                exported.insert((
+                    Output::<Path>(
+                        PathBuf::from(route.0.trim_start_matches('/'))
+                            .join(FILE)
+                            .into(),
+                    ),
+                    Output::<[u8]>(pdf.into()),
+                ));
+            }
+            Err(errors) => diagnostics.report(&errors, &main, origin),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::{fmt::Write as _, path::Path};
+
+use bevy::ecs::{
+    change_detection::DetectChanges as _,
+    component::Component,
+    entity::Entity,
+    query::With,
+    system::{Commands, Query, Res},
+};
+
+use crate::{
+    content::{Output, sitemap::SITEMAP},
+    site::Site,
+};
+
+/// Marker component for the generated `robots.txt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Robots;
+
+/// Generate `robots.txt` from the rules in the site configuration.
+///
+/// Pages marked with [`NoIndex`](crate::content::page::NoIndex) are left out
+/// on purpose, as crawlers that aren't allowed to fetch a page never see that
+/// it asks not to be indexed, and may still index it from links elsewhere.
+pub fn generate(robots: Query<Entity, With<Robots>>, site: Res<Site>, mut commands: Commands) {
+    if !site.is_changed() {
+        return;
+    }
+
+    for robots in robots {
+        commands.entity(robots).despawn();
+    }
+
+    let mut out = String::new();
+    let mut wildcard = false;
+
+    // NOTE: Writing to a `String` never fails.
+    for rule in &site.robots {
+        let user_agents: Vec<&str> = match rule.user_agents.is_empty() {
+            true => vec!["*"],
+            false => rule.user_agents.iter().map(String::as_str).collect(),
+        };
+
+        for user_agent in &user_agents {
+            let _ = writeln!(out, "User-agent: {user_agent}");
+        }
+
+        for allow in &rule.allow {
+            let _ = writeln!(out, "Allow: {allow}");
+        }
+
+        for disallow in &rule.disallow {
+            let _ = writeln!(out, "Disallow: {disallow}");
+        }
+
+        if rule.allow.is_empty() && rule.disallow.is_empty() {
+            let _ = writeln!(out, "Allow: /");
+        }
+
+        let _ = writeln!(out);
+
+        wildcard |= user_agents.contains(&"*");
+    }
+
+    if !wildcard {
+        let _ = writeln!(out, "User-agent: *");
+        let _ = writeln!(out, "Allow: /");
+        let _ = writeln!(out);
+    }
+
+    let sitemap = site.url.join(SITEMAP).unwrap_or_else(|_| site.url.clone());
+    let _ = writeln!(out, "Sitemap: {sitemap}");
+
+    commands.spawn((
+        Robots,
+        Output::<Path>(Path::new("robots.txt").into()),
+        Output::<String>(out),
+    ));
+}
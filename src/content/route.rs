@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Changed, Or, With},
+        system::{Commands, Query, Res},
+    },
+    log::warn,
+};
+use url::Url;
+
+use crate::{
+    Now,
+    content::{
+        Input,
+        collection::{Collection, CollectionConfig},
+        page::{Created, Page, Slug},
+    },
+};
+
+/// The route something is served under, relative to the root of the site.
+///
+/// Routes always start and end with a `/`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct Route(pub String);
+
+impl Route {
+    /// Create a new [`Route`] from a list of path segments.
+    #[must_use]
+    pub fn new<'a>(segments: impl IntoIterator<Item = &'a str>) -> Route {
+        let mut route = String::from("/");
+
+        for segment in segments {
+            let segment = segment.trim_matches('/');
+
+            if !segment.is_empty() {
+                route.push_str(segment);
+                route.push('/');
+            }
+        }
+
+        Route(route)
+    }
+
+    /// The route of the root of the site.
+    #[inline]
+    #[must_use]
+    pub fn root() -> Route {
+        Route("/".into())
+    }
+
+    /// Create a new [`Route`] with `segment` appended to it.
+    #[inline]
+    #[must_use]
+    pub fn join(&self, segment: &str) -> Route {
+        Route::new([&*self.0, segment])
+    }
+
+    /// The path of the document for this route, relative to the output directory.
+    #[inline]
+    #[must_use]
+    pub fn file(&self) -> PathBuf {
+        PathBuf::from(self.0.trim_start_matches('/')).join("index.html")
+    }
+
+    /// The absolute URL of this route.
+    #[inline]
+    #[must_use]
+    pub fn url(&self, base: &Url) -> Url {
+        base.join(self.0.trim_start_matches('/'))
+            .unwrap_or_else(|_| base.clone())
+    }
+}
+
+impl Default for Route {
+    #[inline]
+    fn default() -> Self {
+        Route::root()
+    }
+}
+
+/// Assign each page a route from its slug, falling back to the name of the file.
+///
+/// Pages within a collection are routed according to the URL pattern of the
+/// collection instead.
+pub fn assign(
+    query: Query<
+        (
+            Entity,
+            &Input<Path>,
+            Option<&Slug>,
+            Option<&Created>,
+            Option<&Collection>,
+        ),
+        (
+            With<Page>,
+            Or<(
+                Changed<Slug>,
+                Changed<Input<Path>>,
+                Changed<Created>,
+                Changed<Collection>,
+            )>,
+        ),
+    >,
+    collections: Query<(&Route, &CollectionConfig)>,
+    now: Res<Now>,
+    mut commands: Commands,
+) {
+    for (entity, Input(path), slug, created, collection) in query {
+        let slug = match slug {
+            Some(Slug(slug)) => slug.as_str(),
+            None => path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default(),
+        };
+
+        let route = match collection.and_then(|collection| collections.get(collection.0).ok()) {
+            Some((Route(collection), CollectionConfig { url, .. })) => {
+                let date = match created {
+                    Some(Created(created)) => *created,
+                    None => {
+                        if url.contains("{year}")
+                            || url.contains("{month}")
+                            || url.contains("{day}")
+                        {
+                            warn!("{:?} has no creation date to route it by", path.display());
+                        }
+
+                        now.0.date_naive()
+                    }
+                };
+
+                let route = url
+                    .replace("{collection}", collection)
+                    .replace("{slug}", slug)
+                    .replace("{year}", &date.format("%Y").to_string())
+                    .replace("{month}", &date.format("%m").to_string())
+                    .replace("{day}", &date.format("%d").to_string());
+
+                Route::new(route.split('/'))
+            }
+            None => Route::new(slug.split('/')),
+        };
+
+        commands.entity(entity).insert(route);
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    fmt::{self, Write as _},
+    path::Path,
+};
+
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    query::{Changed, Or, With, Without},
+    system::{Commands, Query, Res},
+};
+use chrono::NaiveDate;
+use url::Url;
+
+use crate::{
+    content::{
+        Output,
+        page::{Created, Draft, Modified, NoIndex, Page},
+        route::Route,
+    },
+    site::Site,
+    util::escaped,
+};
+
+/// Marker component for generated sitemaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Sitemap;
+
+/// The file name of the sitemap, or the sitemap index if there is more than one.
+pub const SITEMAP: &str = "sitemap.xml";
+
+/// The maximum number of URLs a single sitemap may contain.
+pub const MAX_URLS: usize = 50_000;
+
+/// Generate `sitemap.xml`, splitting it into several sitemaps referenced by a
+/// sitemap index once there are too many URLs.
+pub fn generate(
+    pages: Query<
+        (&Route, Option<&Created>, Option<&Modified>),
+        (With<Page>, Without<Draft>, Without<NoIndex>),
+    >,
+    changed: Query<
+        (),
+        (
+            With<Page>,
+            Or<(Changed<Route>, Changed<Created>, Changed<Modified>)>,
+        ),
+    >,
+    sitemaps: Query<Entity, With<Sitemap>>,
+    site: Res<Site>,
+    mut commands: Commands,
+) {
+    if changed.is_empty() {
+        return;
+    }
+
+    for sitemap in sitemaps {
+        commands.entity(sitemap).despawn();
+    }
+
+    let mut urls: Vec<(Url, Option<NaiveDate>)> = pages
+        .iter()
+        .map(|(route, created, modified)| {
+            let lastmod = modified
+                .map(|modified| modified.0)
+                .or(created.map(|created| created.0));
+
+            (route.url(&site.url), lastmod)
+        })
+        .collect();
+
+    urls.sort();
+
+    // NOTE: Writing to a `String` never fails.
+    if urls.len() <= MAX_URLS {
+        let mut sitemap = String::new();
+        let _ = self::sitemap(&mut sitemap, &urls);
+
+        commands.spawn((
+            Sitemap,
+            Output::<Path>(Path::new(SITEMAP).into()),
+            Output::<String>(sitemap),
+        ));
+
+        return;
+    }
+
+    let mut index = Vec::new();
+
+    for (number, chunk) in urls.chunks(MAX_URLS).enumerate() {
+        let path = format!("sitemap-{}.xml", number + 1);
+        let lastmod = chunk.iter().filter_map(|(_, lastmod)| *lastmod).max();
+
+        let mut sitemap = String::new();
+        let _ = self::sitemap(&mut sitemap, chunk);
+
+        index.push((
+            site.url.join(&path).unwrap_or_else(|_| site.url.clone()),
+            lastmod,
+        ));
+
+        commands.spawn((
+            Sitemap,
+            Output::<Path>(Path::new(&path).to_owned().into()),
+            Output::<String>(sitemap),
+        ));
+    }
+
+    let mut sitemap_index = String::new();
+    let _ = self::sitemap_index(&mut sitemap_index, &index);
+
+    commands.spawn((
+        Sitemap,
+        Output::<Path>(Path::new(SITEMAP).into()),
+        Output::<String>(sitemap_index),
+    ));
+}
+
+/// Render a sitemap.
+fn sitemap(out: &mut String, urls: &[(Url, Option<NaiveDate>)]) -> fmt::Result {
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+    )?;
+
+    for (url, lastmod) in urls {
+        writeln!(out, "<url>")?;
+        writeln!(out, "<loc>{}</loc>", escaped(url.as_str()))?;
+
+        if let Some(lastmod) = lastmod {
+            writeln!(out, "<lastmod>{lastmod}</lastmod>")?;
+        }
+
+        writeln!(out, "</url>")?;
+    }
+
+    writeln!(out, "</urlset>")
+}
+
+/// Render a sitemap index.
+fn sitemap_index(out: &mut String, sitemaps: &[(Url, Option<NaiveDate>)]) -> fmt::Result {
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+    )?;
+
+    for (url, lastmod) in sitemaps {
+        writeln!(out, "<sitemap>")?;
+        writeln!(out, "<loc>{}</loc>", escaped(url.as_str()))?;
+
+        if let Some(lastmod) = lastmod {
+            writeln!(out, "<lastmod>{lastmod}</lastmod>")?;
+        }
+
+        writeln!(out, "</sitemap>")?;
+    }
+
+    writeln!(out, "</sitemapindex>")
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        error::Result,
+        query::{Changed, Or, With, Without},
+        system::{Commands, Query, Res},
+    },
+    log::warn,
+};
+use maud::html;
+
+use crate::{
+    content::{
+        Output, feed,
+        head::{Head, OgType},
+        layout::{Layouts, Summary},
+        page::{Created, Description, Draft, Keywords, Page, Title},
+        route::Route,
+    },
+    site::Site,
+    util,
+};
+
+/// The name of the taxonomy built from the keywords of pages.
+pub const TAGS: &str = "tags";
+
+/// The terms of additional taxonomies a page is classified under, keyed by the
+/// name of the taxonomy.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct Terms(pub BTreeMap<String, Vec<String>>);
+
+/// Marker component for generated taxonomy pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct TaxonomyPage;
+
+/// The route of the page listing everything classified under `term`.
+#[must_use]
+pub fn route(taxonomy: &str, term: &str) -> Route {
+    Route::new([taxonomy, &util::slugify(term)])
+}
+
+/// A term within a taxonomy, and the pages classified under it.
+#[derive(Debug, Clone, Default)]
+struct Term<'a> {
+    /// The spellings the term was written with, and how often they're used.
+    spellings: HashMap<&'a str, usize>,
+    pages: Vec<Summary<'a>>,
+}
+
+impl Term<'_> {
+    /// The most commonly used spelling of this term.
+    fn name(&self) -> &str {
+        self.spellings
+            .iter()
+            .max_by(|(lhs, lhs_count), (rhs, rhs_count)| {
+                lhs_count.cmp(rhs_count).then_with(|| rhs.cmp(lhs))
+            })
+            .map(|(spelling, _)| *spelling)
+            .unwrap_or_default()
+    }
+}
+
+/// Generate the index page of each taxonomy, as well as a page for each term
+/// within it.
+pub fn generate(
+    pages: Query<
+        (
+            &Title,
+            &Description,
+            &Keywords,
+            &Terms,
+            &Route,
+            Option<&Created>,
+        ),
+        (With<Page>, Without<Draft>),
+    >,
+    changed: Query<
+        (),
+        (
+            With<Page>,
+            Or<(Changed<Keywords>, Changed<Terms>, Changed<Route>)>,
+        ),
+    >,
+    generated: Query<Entity, With<TaxonomyPage>>,
+    layouts: Res<Layouts>,
+    site: Res<Site>,
+    mut commands: Commands,
+) -> Result<()> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    for entity in generated {
+        commands.entity(entity).despawn();
+    }
+
+    let mut taxonomies: BTreeMap<&str, (&str, BTreeMap<String, Term>)> = BTreeMap::new();
+
+    taxonomies.insert(TAGS, ("Tags", BTreeMap::new()));
+
+    for taxonomy in &site.taxonomies {
+        let title = match &*taxonomy.title {
+            "" => &*taxonomy.name,
+            title => title,
+        };
+
+        taxonomies.insert(&taxonomy.name, (title, BTreeMap::new()));
+    }
+
+    for (
+        Title(title),
+        Description(description),
+        Keywords(keywords),
+        Terms(terms),
+        route,
+        created,
+    ) in &pages
+    {
+        let summary = Summary {
+            title,
+            description,
+            route,
+            created: created.map(|created| created.0),
+        };
+
+        let terms = terms
+            .iter()
+            .map(|(taxonomy, terms)| (&**taxonomy, terms))
+            .chain([(TAGS, keywords)]);
+
+        for (taxonomy, terms) in terms {
+            let Some((_, taxonomy_terms)) = taxonomies.get_mut(taxonomy) else {
+                warn!("{title:?} uses the unknown taxonomy {taxonomy:?}");
+                continue;
+            };
+
+            // NOTE: Terms may be spelled differently, yet share a slug, such
+            //       as `Rust` and `rust`.
+            let mut slugs = HashSet::new();
+
+            for term in terms {
+                let name = term.trim();
+                let slug = util::slugify(name);
+
+                if slug.is_empty() {
+                    warn!("{title:?} has the empty {taxonomy} term {term:?}");
+                    continue;
+                }
+
+                let first = slugs.insert(slug.clone());
+                let term = taxonomy_terms.entry(slug).or_default();
+
+                *term.spellings.entry(name).or_default() += 1;
+
+                if first {
+                    term.pages.push(summary);
+                }
+            }
+        }
+    }
+
+    for (taxonomy, (title, mut terms)) in taxonomies {
+        warn_near_duplicates(taxonomy, &terms);
+
+        let taxonomy_route = Route::new([taxonomy]);
+
+        for term in terms.values_mut() {
+            term.pages.sort_by(|lhs, rhs| {
+                rhs.created
+                    .cmp(&lhs.created)
+                    .then_with(|| lhs.title.cmp(rhs.title))
+            });
+        }
+
+        let main = html! {
+            div.taxonomy {
+                h1 { (title) }
+
+                ul.terms {
+                    @for (slug, term) in &terms {
+                        li {
+                            a href = (taxonomy_route.join(slug).0) { (term.name()) }
+                            " "
+                            span.count { "(" (term.pages.len()) ")" }
+                        }
+                    }
+                }
+            }
+        };
+
+        let head = Head {
+            title: format!("{title} · {}", site.title),
+            description: site.description.clone(),
+            url: taxonomy_route.url(&site.url),
+            og_type: OgType::Website,
+            links: Vec::new(),
+            robots: None,
+        };
+
+        let document = layouts.render_generated(
+            site.listing_layout.as_deref(),
+            &site,
+            &head,
+            title,
+            &taxonomy_route,
+            main,
+        )?;
+
+        commands.spawn((
+            TaxonomyPage,
+            Output::<Path>(taxonomy_route.file().into()),
+            Output::<String>(document.into_string()),
+            taxonomy_route.clone(),
+        ));
+
+        for (slug, term) in &terms {
+            let route = taxonomy_route.join(slug);
+            let name = term.name();
+
+            let main = html! {
+                div.term {
+                    h1 {
+                        a href = (taxonomy_route.0) { (title) }
+                        " / "
+                        (name)
+                    }
+
+                    @for summary in &term.pages {
+                        (summary)
+                    }
+                }
+            };
+
+            let head = Head {
+                title: format!("{name} · {}", site.title),
+                description: site.description.clone(),
+                url: route.url(&site.url),
+                og_type: OgType::Website,
+                links: match taxonomy {
+                    TAGS => feed::links(&format!("{} · {name}", site.title), &route),
+                    _ => Vec::new(),
+                },
+                robots: None,
+            };
+
+            let document = layouts.render_generated(
+                site.listing_layout.as_deref(),
+                &site,
+                &head,
+                name,
+                &route,
+                main,
+            )?;
+
+            commands.spawn((
+                TaxonomyPage,
+                Output::<Path>(route.file().into()),
+                Output::<String>(document.into_string()),
+                route,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Warn about terms that are likely meant to be the same, such as `type` and
+/// `types`, or `typescript` and `type-script`.
+fn warn_near_duplicates(taxonomy: &str, terms: &BTreeMap<String, Term>) {
+    let slugs: Vec<&str> = terms.keys().map(String::as_str).collect();
+
+    for (index, lhs) in slugs.iter().enumerate() {
+        for rhs in &slugs[index + 1..] {
+            let near_duplicate = lhs.replace('-', "") == rhs.replace('-', "")
+                || (lhs.chars().count().min(rhs.chars().count()) >= 4
+                    && edit_distance(lhs, rhs) <= 1);
+
+            if near_duplicate {
+                warn!(
+                    "the {taxonomy} {:?} and {:?} look like duplicates",
+                    terms[*lhs].name(),
+                    terms[*rhs].name(),
+                );
+            }
+        }
+    }
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs: Vec<char> = rhs.chars().collect();
+    let mut previous: Vec<usize> = (0..=rhs.len()).collect();
+    let mut current = vec![0; rhs.len() + 1];
+
+    for (i, lhs) in lhs.chars().enumerate() {
+        current[0] = i + 1;
+
+        for (j, rhs) in rhs.iter().enumerate() {
+            let substitution = previous[j] + usize::from(lhs != *rhs);
+
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[rhs.len()]
+}
@@ -0,0 +1,170 @@
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    query::{Changed, Or, With},
+    system::{Commands, Query},
+};
+use jotdown::{Container, Event};
+use maud::{Markup, Render, html};
+use serde::{Deserialize, Serialize};
+
+use crate::content::{
+    djot::{DjotEvents, plain_text},
+    page::Page,
+};
+
+/// Whether a page has a table of contents, as set by the `toc` key in its
+/// front matter.
+///
+/// Either a boolean, or the deepest heading level to include.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, Component,
+)]
+#[serde(untagged)]
+pub enum Toc {
+    Enabled(bool),
+    Depth(u16),
+}
+
+impl Toc {
+    /// The deepest heading level to include, if any.
+    #[must_use]
+    pub fn depth(self) -> Option<u16> {
+        match self {
+            Toc::Enabled(true) => Some(u16::MAX),
+            Toc::Enabled(false) | Toc::Depth(0) => None,
+            Toc::Depth(depth) => Some(depth),
+        }
+    }
+}
+
+impl Default for Toc {
+    #[inline]
+    fn default() -> Self {
+        Toc::Enabled(true)
+    }
+}
+
+/// A heading within a table of contents.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entry {
+    /// The plain text of the heading.
+    pub text: String,
+    /// The level of the heading.
+    pub level: u16,
+    /// The id of the heading, which it may be linked to with.
+    pub id: String,
+    /// The headings nested under this one.
+    pub children: Vec<Entry>,
+}
+
+/// The table of contents of a page.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct TableOfContents(pub Vec<Entry>);
+
+impl TableOfContents {
+    /// Build a table of contents from the headings within `events`, skipping
+    /// any deeper than `depth`.
+    #[must_use]
+    pub fn new(events: &[Event<'static>], depth: u16) -> TableOfContents {
+        let mut entries = Vec::new();
+        let mut heading: Option<Entry> = None;
+        let mut nesting = 0usize;
+
+        for event in events {
+            match event {
+                Event::Start(Container::Heading { level, id, .. }, _) if heading.is_none() => {
+                    heading = Some(Entry {
+                        text: String::new(),
+                        level: *level,
+                        id: id.to_string(),
+                        children: Vec::new(),
+                    });
+                }
+                Event::Start(..) if heading.is_some() => nesting += 1,
+                Event::End(..) if heading.is_some() && nesting > 0 => nesting -= 1,
+                Event::End(Container::Heading { .. }) => {
+                    if let Some(mut entry) = heading.take() {
+                        entry.text = entry.text.trim().to_owned();
+
+                        if entry.level <= depth && !entry.id.is_empty() {
+                            insert(&mut entries, entry);
+                        }
+                    }
+                }
+                event => {
+                    if let (Some(heading), Some(s)) = (&mut heading, plain_text(event)) {
+                        heading.text.push_str(s);
+                    }
+                }
+            }
+        }
+
+        TableOfContents(entries)
+    }
+
+    /// Returns whether there are no headings.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Insert `entry` under the last entry of a lower level.
+fn insert(entries: &mut Vec<Entry>, entry: Entry) {
+    match entries.last_mut() {
+        Some(last) if last.level < entry.level => insert(&mut last.children, entry),
+        _ => entries.push(entry),
+    }
+}
+
+impl Render for TableOfContents {
+    fn render(&self) -> Markup {
+        fn list(entries: &[Entry]) -> Markup {
+            html! {
+                ol {
+                    @for entry in entries {
+                        li {
+                            a href = (format!("#{}", entry.id)) { (entry.text) }
+
+                            @if !entry.children.is_empty() {
+                                (list(&entry.children))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        html! {
+            @if !self.is_empty() {
+                nav.toc aria-label = "Table of contents" {
+                    (list(&self.0))
+                }
+            }
+        }
+    }
+}
+
+/// Build the table of contents of every page that has one.
+pub fn build(
+    query: Query<
+        (Entity, &DjotEvents, Option<&Toc>),
+        (With<Page>, Or<(Changed<DjotEvents>, Changed<Toc>)>),
+    >,
+    mut commands: Commands,
+) {
+    for (entity, DjotEvents(events), toc) in query {
+        match toc.copied().unwrap_or_default().depth() {
+            Some(depth) => {
+                commands
+                    .entity(entity)
+                    .insert(TableOfContents::new(events, depth));
+            }
+            None => {
+                commands.entity(entity).remove::<TableOfContents>();
+            }
+        }
+    }
+}
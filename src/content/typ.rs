@@ -0,0 +1,263 @@
+//! Pages written in typst.
+//!
+//! Every `.typ` file within the site is a page, except for the print template
+//! of the site, see [`Site::print_template`], and files starting with `_`,
+//! which are reserved. Typst modules that pages import, such as
+//! `_figures.typ`, need to start with `_` as a result, lest they be published
+//! as pages of their own.
+
+use std::path::Path;
+
+use anyhow::{Context as _, anyhow};
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    error::Result,
+    query::{Changed, Or, With},
+    system::{Commands, Query, Res, ResMut},
+};
+use maud::{PreEscaped, html};
+use serde::{Deserialize, Serialize};
+use typst::{
+    diag::Warned,
+    foundations::{Label, Selector},
+    introspection::MetadataElem,
+    layout::PagedDocument,
+    syntax::Source,
+    utils::PicoStr,
+};
+
+use crate::{
+    content::{
+        Input,
+        front_matter::FrontMatter,
+        page::{Body, PageMatter, PageOffset},
+    },
+    site::Site,
+    typst::{
+        diagnostic::{Diagnostics, Origin},
+        files::Dependencies,
+        inputs::{Inputs, PageInputs},
+        svg,
+        world::{Compiled, TypstWorld},
+    },
+};
+
+/// Marker component for pages written in typst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Component)]
+pub struct TypstPage;
+
+/// How a page written in typst is exported, as set by the `export` key in its
+/// front matter.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Component,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Export {
+    /// Every page of the document as an SVG.
+    #[default]
+    Svg,
+    /// The document as HTML, with typst's HTML export.
+    Html,
+}
+
+/// The label of the `metadata` element a page may be described by, such as:
+///
+/// ```typst
+/// #metadata((title: "Hello", created: "2025-01-01")) <page>
+/// ```
+pub const METADATA: &str = "page";
+
+/// Returns the front matter within the block comment a page starts with, if
+/// any, such as:
+///
+/// ```typst
+/// /*
+/// ---
+/// title = "Hello"
+/// ---
+/// */
+/// ```
+#[must_use]
+pub fn front_matter(contents: &str) -> Option<FrontMatter<'_>> {
+    let comment = contents.trim_start().strip_prefix("/*")?;
+    let (comment, _) = comment.split_once("*/")?;
+
+    FrontMatter::parse(comment.trim_start()).ok()
+}
+
+/// Returns the matter described by the `metadata` element labelled
+/// [`METADATA`] within `document`, if there is one.
+pub fn metadata(document: &PagedDocument, path: &Path) -> Result<Option<PageMatter>> {
+    let Some(label) = Label::new(PicoStr::intern(METADATA)) else {
+        return Ok(None);
+    };
+
+    let elements = document.introspector.query(&Selector::Label(label));
+
+    let Some(element) = elements.first() else {
+        return Ok(None);
+    };
+
+    let Some(metadata) = element.to_packed::<MetadataElem>() else {
+        return Err(anyhow!(
+            "<{METADATA}> labels something other than metadata in {:?}",
+            path.display()
+        )
+        .into());
+    };
+
+    let context = || format!("reading the metadata of {:?}", path.display());
+    let value = toml::Value::try_from(&metadata.value).with_context(context)?;
+    let matter = value.try_into().with_context(context)?;
+
+    Ok(Some(matter))
+}
+
+/// Returns the body of an HTML document.
+fn html_body(document: &str) -> &str {
+    let start = document
+        .find("<body>")
+        .map_or(0, |start| start + "<body>".len());
+    let end = document.rfind("</body>").unwrap_or(document.len());
+
+    document.get(start..end).unwrap_or(document).trim()
+}
+
+/// Report the diagnostics of something compiled from `main`, returning its
+/// output if it compiled, along with what it depends on.
+fn report<T>(
+    compiled: Compiled<T>,
+    main: &Source,
+    origin: Origin,
+    diagnostics: &mut Diagnostics,
+) -> (Option<T>, Dependencies) {
+    let Compiled {
+        warned: Warned { output, warnings },
+        dependencies,
+    } = compiled;
+
+    diagnostics.report(&warnings, main, origin);
+
+    let output = match output {
+        Ok(output) => Some(output),
+        Err(errors) => {
+            diagnostics.report(&errors, main, origin);
+            None
+        }
+    };
+
+    (output, dependencies)
+}
+
+/// Read the metadata of every page written in typst, from its front matter,
+/// or by compiling it otherwise.
+///
+/// Pages are only rendered once they've been routed, see [`render`].
+pub fn load(
+    query: Query<(Entity, &Input<String>, &Input<Path>), (With<TypstPage>, Changed<Input<String>>)>,
+    site: Res<Site>,
+    world: Res<TypstWorld>,
+    mut commands: Commands,
+) -> Result<()> {
+    for (entity, Input(contents), Input(path)) in query {
+        let matter = match front_matter(contents) {
+            Some(front_matter) => PageMatter::parse(Some(&front_matter), path)?,
+            // NOTE: Pages described by metadata only know of it once
+            //       compiled, so they're compiled with the inputs of the site
+            //       alone. Any diagnostics are reported once they're rendered.
+            None => {
+                let main = TypstWorld::file_source(path, contents.to_string());
+                let compiled = world.compile(&main, &Inputs::new(&site, PageInputs::default()));
+
+                match &compiled.warned.output {
+                    Ok(document) => metadata(document, path)?.unwrap_or_default(),
+                    Err(_) => PageMatter::default(),
+                }
+            }
+        };
+
+        let mut entity = commands.entity(entity);
+
+        // NOTE: Typst pages are compiled whole, comment and all.
+        entity.insert((PageOffset(0), matter.export));
+        matter.insert(&mut entity);
+    }
+
+    Ok(())
+}
+
+/// Render the body of every page written in typst, with the inputs of the
+/// page, see [`Inputs`].
+pub fn render(
+    query: Query<
+        (Entity, &Input<String>, &Input<Path>, &Export, &Inputs),
+        (
+            With<TypstPage>,
+            Or<(Changed<Input<String>>, Changed<Export>, Changed<Inputs>)>,
+        ),
+    >,
+    world: Res<TypstWorld>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut commands: Commands,
+) {
+    for (entity, Input(contents), Input(path), export, inputs) in query {
+        let main = TypstWorld::file_source(path, contents.to_string());
+        let origin = Origin {
+            path,
+            contents,
+            start: 0,
+            offset: 0,
+        };
+
+        let (body, dependencies) = match export {
+            Export::Svg => {
+                let compiled = world.compile(&main, inputs);
+                let (document, dependencies) = report(compiled, &main, origin, &mut diagnostics);
+
+                let body = document.map(|document| {
+                    html! {
+                        @for page in &document.pages {
+                            div.typst-page {
+                                (PreEscaped(svg::svg(page)))
+                            }
+                        }
+                    }
+                    .into_string()
+                });
+
+                (body, dependencies)
+            }
+            Export::Html => {
+                let compiled = world.compile_html(&main, inputs);
+                let (document, dependencies) = report(compiled, &main, origin, &mut diagnostics);
+
+                let body = document.map(|document| html_body(&document).to_owned());
+
+                (body, dependencies)
+            }
+        };
+
+        let mut entity = commands.entity(entity);
+
+        entity.insert(dependencies);
+
+        let Some(body) = body else {
+            entity.remove::<Body>();
+            continue;
+        };
+
+        entity.insert(Body(body));
+    }
+}
@@ -1,6 +1,9 @@
+use std::time::Duration;
+
 use ::bevy::{
-    app::{App, Startup},
-    ecs::{component::Component, resource::Resource, schedule::IntoScheduleConfigs},
+    app::{App, ScheduleRunnerPlugin, Startup, Update},
+    ecs::{component::Component, error::warn, resource::Resource, schedule::IntoScheduleConfigs},
+    log::LogPlugin,
 };
 use chrono::{DateTime, Utc};
 
@@ -8,25 +11,98 @@ use chrono::{DateTime, Utc};
 pub mod bevy;
 /// Tools relating to content.
 pub mod content;
+/// Site wide configuration.
+pub mod site;
 /// Tools for typst.
 pub mod typst;
 /// Utilities.
 pub mod util;
 
+/// How often the site is checked for changes while watching it.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
 pub fn main() {
-    App::new()
+    let mut app = App::new();
+
+    app.add_plugins(LogPlugin::default())
         .init_resource::<Now>()
+        .init_resource::<content::layout::Layouts>()
+        .init_resource::<typst::diagnostic::Diagnostics>()
+        .add_message::<typst::files::FileChanged>()
         .add_systems(
             Startup,
             (
-                content::page::find,
-                content::page::read,
+                (site::load, typst::world::init),
+                (
+                    content::page::find,
+                    content::collection::find,
+                    content::data::find,
+                ),
+            )
+                .chain(),
+        )
+        // NOTE: Every system from here on only acts on what has changed, so
+        // running them again rebuilds just what's needed.
+        .add_systems(
+            Update,
+            (
+                content::watch,
+                (
+                    content::page::read,
+                    content::collection::load,
+                    content::data::read,
+                ),
                 content::page::load_matter,
+                typst::files::expose,
+                (typst::files::poll, typst::files::sync),
+                typst::files::invalidate,
+                content::typ::load,
+                content::collection::assign,
                 content::djot::parse_events,
+                (content::route::assign, content::numbering::number),
+                (
+                    content::math::extract,
+                    content::diagram::extract,
+                    typst::inputs::assign,
+                ),
+                (
+                    content::math::render,
+                    content::math::convert,
+                    content::diagram::render,
+                    content::typ::render,
+                ),
+                (
+                    content::djot::render,
+                    content::toc::build,
+                    content::pdf::generate,
+                    content::history::load,
+                ),
+                (
+                    content::page::render,
+                    content::feed::generate,
+                    content::sitemap::generate,
+                    content::robots::generate,
+                    content::taxonomy::generate,
+                    content::listing::generate,
+                    content::epub::generate,
+                ),
+                content::history::render,
+                typst::world::evict,
+                typst::diagnostic::check,
+                content::output::write,
             )
                 .chain(),
-        )
-        .run();
+        );
+
+    // NOTE: `glados watch` rebuilds the site whenever its sources change,
+    // rather than exiting after the first build. Errors are only logged then,
+    // so that a broken page doesn't end the session.
+    if std::env::args().nth(1).as_deref() == Some("watch") {
+        app.add_plugins(ScheduleRunnerPlugin::run_loop(WATCH_INTERVAL))
+            .set_error_handler(warn);
+    }
+
+    app.run();
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component, Resource)]
@@ -0,0 +1,197 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use bevy::ecs::{error::Result, resource::Resource, system::Commands};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::content::math::MathOutput;
+
+/// Site wide configuration, read from `site.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Resource)]
+#[serde(default)]
+pub struct Site {
+    /// The title of the site.
+    pub title: String,
+    /// The description of the site.
+    pub description: String,
+    /// The URL the site is served under.
+    pub url: Url,
+    /// The language of the site.
+    pub language: String,
+    /// The author of the site.
+    pub author: Author,
+    /// The directory that output is written to.
+    pub output: PathBuf,
+    /// The rules to publish in `robots.txt`.
+    pub robots: Vec<RobotsRule>,
+    /// Additional taxonomies pages may be classified by, besides their keywords.
+    pub taxonomies: Vec<Taxonomy>,
+    /// The amount of pages per page of listings on the home page.
+    pub paginate: usize,
+    /// The layout the home page, the archive and taxonomy pages are rendered
+    /// with, see [`Layouts::LISTING`](crate::content::layout::Layouts::LISTING).
+    pub listing_layout: Option<String>,
+    /// Additional callouts, keyed by the class of the div they're made from.
+    ///
+    /// These take precedence over the built in callouts, see [`Callout::builtin`].
+    pub callouts: BTreeMap<String, Callout>,
+    /// How theorems, lemmas and definitions are numbered.
+    pub theorems: Theorems,
+    /// The typst file pages are printed with when exported to PDF, relative
+    /// to the root of the site.
+    ///
+    /// See [`crate::content::pdf::TEMPLATE`] for the template used otherwise.
+    pub print_template: Option<PathBuf>,
+    /// How math is rendered, unless a page says otherwise.
+    pub math_output: MathOutput,
+    /// Macros available to math written in LaTeX, keyed by their name, such
+    /// as `R = '\mathbb{R}'`.
+    ///
+    /// Parameters are written as `#1` to `#9`, such as
+    /// `norm = '\left\lVert #1 \right\rVert'`.
+    pub latex_macros: BTreeMap<String, String>,
+}
+
+/// The author of the site.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Author {
+    /// The name of the author.
+    pub name: String,
+    /// The email address of the author.
+    pub email: Option<String>,
+    /// The website of the author.
+    pub url: Option<Url>,
+}
+
+/// A group of rules within `robots.txt`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RobotsRule {
+    /// The user agents these rules apply to.
+    pub user_agents: Vec<String>,
+    /// The paths that may be crawled.
+    pub allow: Vec<String>,
+    /// The paths that may not be crawled.
+    pub disallow: Vec<String>,
+}
+
+/// A taxonomy pages may be classified by, such as `series` or `category`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct Taxonomy {
+    /// The name of the taxonomy, as used in front matter and routes.
+    pub name: String,
+    /// The title of the taxonomy, as displayed on its pages.
+    #[serde(default)]
+    pub title: String,
+    /// Whether the pages classified under each term are bundled into an
+    /// EPUB, such as for a series.
+    #[serde(default)]
+    pub epub: bool,
+}
+
+/// A callout, such as a note or a warning, made from a div with its class.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Callout {
+    /// The title shown when the callout doesn't start with a heading.
+    pub title: String,
+    /// The icon shown next to the title.
+    pub icon: String,
+    /// Whether the callout is collapsed within a `<details>`.
+    pub details: bool,
+}
+
+impl Callout {
+    /// Returns the built in callout for `class`, if there is one.
+    #[must_use]
+    pub fn builtin(class: &str) -> Option<Callout> {
+        let (title, icon, details) = match class {
+            "note" => ("Note", "ℹ", false),
+            "warning" => ("Warning", "⚠", false),
+            "tip" => ("Tip", "★", false),
+            "aside" => ("Aside", "※", false),
+            "details" => ("Details", "", true),
+            _ => return None,
+        };
+
+        Some(Callout {
+            title: title.into(),
+            icon: icon.into(),
+            details,
+        })
+    }
+}
+
+/// How theorems, lemmas and definitions are numbered.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Theorems {
+    /// Each is numbered by a counter of its own, such as `Theorem 1` followed
+    /// by `Lemma 1`.
+    #[default]
+    Independent,
+    /// All are numbered by the same counter, such as `Theorem 1` followed by
+    /// `Lemma 2`.
+    Shared,
+}
+
+impl Site {
+    /// The path to the site configuration.
+    pub const PATH: &str = "site.toml";
+
+    /// Returns the callout for divs with `class`, if there is one.
+    #[must_use]
+    pub fn callout(&self, class: &str) -> Option<Callout> {
+        self.callouts
+            .get(class)
+            .cloned()
+            .or_else(|| Callout::builtin(class))
+    }
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Site {
+            title: String::new(),
+            description: String::new(),
+            url: Url::parse("http://localhost:8080/").unwrap(),
+            language: "en".into(),
+            author: Author::default(),
+            output: PathBuf::from("public"),
+            robots: Vec::new(),
+            taxonomies: Vec::new(),
+            paginate: 10,
+            listing_layout: None,
+            callouts: BTreeMap::new(),
+            theorems: Theorems::Independent,
+            print_template: None,
+            math_output: MathOutput::Svg,
+            latex_macros: BTreeMap::new(),
+        }
+    }
+}
+
+pub fn load(mut commands: Commands) -> Result<()> {
+    let path = Path::new(Site::PATH);
+
+    let site = if path.exists() {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("reading {:?}", path.display()))?;
+
+        toml::from_str(&contents)
+            .with_context(|| format!("parsing {:?} as toml", path.display()))?
+    } else {
+        Site::default()
+    };
+
+    commands.insert_resource::<Site>(site);
+
+    Ok(())
+}
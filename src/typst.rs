@@ -1 +1,5 @@
+pub mod diagnostic;
 pub mod files;
+pub mod inputs;
+pub mod svg;
+pub mod world;
@@ -0,0 +1,228 @@
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use bevy::{
+    ecs::{error::Result, resource::Resource, system::ResMut},
+    log::{error, warn},
+};
+use typst::{
+    diag::{Severity, SourceDiagnostic},
+    syntax::Source,
+};
+
+use crate::util;
+
+/// Where the code within the source of a generated typst document came from.
+#[derive(Debug, Clone, Copy)]
+pub struct Origin<'a> {
+    /// The file the code came from.
+    pub path: &'a Path,
+    /// The contents of the file.
+    pub contents: &'a str,
+    /// Where the code starts within the generated source.
+    pub start: usize,
+    /// Where the code starts within the contents of the file.
+    pub offset: usize,
+}
+
+/// A location within a file.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub path: PathBuf,
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The column in characters, starting at 1.
+    pub column: usize,
+    /// The text of the line.
+    pub snippet: String,
+    /// The amount of characters to underline.
+    pub length: usize,
+}
+
+/// A typst diagnostic, mapped back to the file its code came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub hints: Vec<String>,
+    /// The path the diagnostic is attributed to, if it couldn't be located
+    /// any more precisely.
+    pub path: PathBuf,
+    /// Where the diagnostic occurred, if it occurred within the code itself
+    /// rather than any of the code generated around it.
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    /// Map a diagnostic from compiling `main` back to where its code came from.
+    #[must_use]
+    pub fn new(diagnostic: &SourceDiagnostic, main: &Source, origin: Origin) -> Diagnostic {
+        let range = main
+            .range(diagnostic.span)
+            .filter(|range| range.start >= origin.start)
+            .map(|range| range.start - origin.start..range.end - origin.start);
+
+        Diagnostic::at(
+            diagnostic.severity,
+            diagnostic.message.to_string(),
+            diagnostic.hints.iter().map(ToString::to_string).collect(),
+            range,
+            origin,
+        )
+    }
+
+    /// Create a diagnostic at `range` within the code itself, such as for
+    /// something reported before the code is compiled.
+    #[must_use]
+    pub fn at(
+        severity: Severity,
+        message: String,
+        hints: Vec<String>,
+        range: Option<Range<usize>>,
+        origin: Origin,
+    ) -> Diagnostic {
+        let location = range.map(|range| {
+            let start = origin.offset + range.start;
+            let end = origin.offset + range.end;
+            let (line, column) = util::line_column(origin.contents, start);
+
+            let snippet = origin
+                .contents
+                .lines()
+                .nth(line - 1)
+                .unwrap_or_default()
+                .trim_end();
+
+            let length = origin
+                .contents
+                .get(start..end.min(origin.contents.len()))
+                .map_or(1, |text| {
+                    text.lines().next().unwrap_or_default().chars().count()
+                })
+                .max(1);
+
+            Location {
+                path: origin.path.to_owned(),
+                line,
+                column,
+                snippet: snippet.to_owned(),
+                length,
+            }
+        });
+
+        Diagnostic {
+            severity,
+            message,
+            hints,
+            path: origin.path.to_owned(),
+            location,
+        }
+    }
+}
+
+/// Formats a diagnostic like rustc does, such as:
+///
+/// ```text
+/// error: unknown variable: foo
+///   --> posts/hello.dj:12:5
+///    |
+/// 12 | $ foo + 1 $
+///    |   ^^^
+///    = hint: ...
+/// ```
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        writeln!(f, "{severity}: {}", self.message)?;
+
+        let width = match &self.location {
+            Some(location) => location.line.to_string().len(),
+            None => 1,
+        };
+        let gutter = " ".repeat(width);
+
+        match &self.location {
+            Some(Location {
+                path,
+                line,
+                column,
+                snippet,
+                length,
+            }) => {
+                writeln!(f, "{gutter}--> {}:{line}:{column}", path.display())?;
+                writeln!(f, "{gutter} |")?;
+                writeln!(f, "{line} | {snippet}")?;
+                write!(
+                    f,
+                    "{gutter} | {}{}",
+                    " ".repeat(column - 1),
+                    "^".repeat(*length)
+                )?;
+            }
+            None => write!(
+                f,
+                "{gutter}--> {} (within generated code)",
+                self.path.display()
+            )?,
+        }
+
+        for hint in &self.hints {
+            write!(f, "\n{gutter} = hint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The diagnostics reported while building the site.
+///
+/// Failing to compile something with typst doesn't stop the build, so that
+/// every error is reported at once. The build fails before any output is
+/// written instead, see [`check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub struct Diagnostics {
+    /// The amount of errors reported.
+    pub errors: usize,
+}
+
+impl Diagnostics {
+    /// Report `diagnostics` from compiling `main`.
+    pub fn report<'d>(
+        &mut self,
+        diagnostics: impl IntoIterator<Item = &'d SourceDiagnostic>,
+        main: &Source,
+        origin: Origin,
+    ) {
+        for diagnostic in diagnostics {
+            self.push(Diagnostic::new(diagnostic, main, origin));
+        }
+    }
+
+    /// Report a single diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        match diagnostic.severity {
+            Severity::Error => {
+                self.errors += 1;
+                error!("{diagnostic}");
+            }
+            Severity::Warning => warn!("{diagnostic}"),
+        }
+    }
+}
+
+/// Fail the build if any errors have been reported.
+pub fn check(mut diagnostics: ResMut<Diagnostics>) -> Result<()> {
+    match std::mem::take(&mut diagnostics.errors) {
+        0 => Ok(()),
+        1 => Err(anyhow!("could not build the site due to a previous error").into()),
+        errors => Err(anyhow!("could not build the site due to {errors} previous errors").into()),
+    }
+}
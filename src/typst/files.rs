@@ -1,14 +1,24 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
     io::ErrorKind,
     mem,
     path::{Path, PathBuf},
     str::Utf8Error,
+    time::SystemTime,
 };
 
-use bevy::ecs::{component::Component, entity::Entity, resource::Resource};
+use bevy::ecs::{
+    change_detection::DetectChangesMut as _,
+    component::Component,
+    entity::Entity,
+    lifecycle::RemovedComponents,
+    message::{Message, MessageReader, MessageWriter},
+    query::{Changed, Has, Or, With},
+    resource::Resource,
+    system::{Commands, Query, Res, ResMut},
+};
 use parking_lot::Mutex;
 use typst::{
     diag::{FileError, FileResult},
@@ -16,7 +26,18 @@ use typst::{
 };
 use typst_kit::{download::ProgressSink, package::PackageStorage};
 
-use crate::util;
+use crate::{
+    content::{
+        Input,
+        diagram::Diagram,
+        math::Math,
+        page::{Page, Slug},
+        pdf::PdfOf,
+        typ::TypstPage,
+    },
+    typst::{inputs::Inputs, world::TypstWorld},
+    util,
+};
 
 #[derive(Debug, PartialEq, Eq, Hash, Component, Resource)]
 pub enum FileData {
@@ -203,15 +224,57 @@ impl Clone for FileData {
 pub enum FileLoc<'a> {
     /// A file location on disk.
     Disk(&'a Path),
-    /// A file attached to an entity...
+    /// A file attached to an entity, see [`TypstPath`].
     Entity(Entity),
 }
 
+/// The virtual path an entity exposes its [`FileData`] to typst under.
+///
+/// These live under directories starting with an `@`, so that they don't
+/// collide with files on disk:
+///
+/// - `/@site/pages/<slug>.dj` for the source of each page, or `.typ` for pages
+///   written in typst.
+/// - `/@data/<path>` for each file within the `data` directory.
+///
+/// Such as:
+///
+/// ```typst
+/// #let links = toml("/@data/links.toml")
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
+pub struct TypstPath(pub String);
+
+/// The files something compiled with typst accessed, so that it can be
+/// compiled again when any of them change.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Component)]
+pub struct Dependencies {
+    /// Every file accessed, besides the main source.
+    pub files: HashSet<FileId>,
+}
+
+impl Dependencies {
+    /// Returns whether any of `files` are depended on.
+    #[must_use]
+    pub fn any(&self, files: &HashSet<FileId>) -> bool {
+        !self.files.is_disjoint(files)
+    }
+}
+
+/// Sent when a file typst may have accessed changes, so that everything that
+/// depends on it is compiled again, see [`invalidate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Message)]
+pub struct FileChanged(pub FileId);
+
 /// A typst file entry that is lazily loaded.
 #[derive(Debug, Default)]
 pub struct FileEntry {
     accessed: bool,
     result: Option<FileResult<FileData>>,
+    /// The path the file was read from, if it was read from disk.
+    path: Option<PathBuf>,
+    /// When the file was last modified, as of when it was read.
+    modified: Option<SystemTime>,
 }
 
 impl FileEntry {
@@ -220,6 +283,20 @@ impl FileEntry {
         self.accessed
     }
 
+    /// Returns whether the file has been modified on disk since it was read.
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        modified != self.modified
+    }
+
     pub fn get_or_init(
         &mut self,
         loc: FileLoc,
@@ -230,6 +307,11 @@ impl FileEntry {
         } else {
             self.result.insert(match loc {
                 FileLoc::Disk(path) => {
+                    self.path = Some(path.to_owned());
+                    self.modified = fs::metadata(path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok();
+
                     fs::read(path)
                         .map(Cow::Owned)
                         .map(FileData::new)
@@ -246,9 +328,13 @@ impl FileEntry {
         }
     }
 
+    /// Mark the file to be read again the next time it's accessed.
     #[inline]
     pub fn reset(&mut self) {
         self.accessed = false;
+        // NOTE: The file isn't stale anymore until it's read again.
+        self.path = None;
+        self.modified = None;
     }
 }
 
@@ -256,7 +342,7 @@ impl FileEntry {
 
 /// The storage for typst files.
 #[derive(Debug, Default, Component, Resource)]
-pub struct Files(pub Mutex<HashMap<FileId, FileData>>);
+pub struct Files(pub Mutex<HashMap<FileId, FileEntry>>);
 
 impl Files {
     pub fn resolve<'l>(
@@ -288,3 +374,123 @@ impl Files {
             })
     }
 }
+
+/// Expose the source of every page to typst, under the slug of the page.
+///
+/// Pages written in typst are exposed under the name of their file instead, as
+/// their slug isn't known until they've been compiled.
+pub fn expose(
+    query: Query<
+        (
+            Entity,
+            &Input<Path>,
+            &Input<String>,
+            Option<&Slug>,
+            Has<TypstPage>,
+        ),
+        (With<Page>, Or<(Changed<Input<String>>, Changed<Slug>)>),
+    >,
+    mut commands: Commands,
+) {
+    for (entity, Input(path), Input(contents), slug, typst) in query {
+        let slug = match slug {
+            Some(Slug(slug)) => slug.as_str(),
+            None => path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default(),
+        };
+
+        let extension = match typst {
+            true => "typ",
+            false => "dj",
+        };
+
+        commands.entity(entity).insert((
+            TypstPath(format!(
+                "/@site/pages/{}.{extension}",
+                slug.trim_matches('/')
+            )),
+            FileData::Text(contents.clone().into()),
+        ));
+    }
+}
+
+/// Make every file exposed by an entity available to typst, forgetting the
+/// files of entities that no longer expose one, or that have since moved.
+pub fn sync(
+    query: Query<(Entity, &TypstPath, &FileData), Or<(Changed<TypstPath>, Changed<FileData>)>>,
+    mut removed: RemovedComponents<TypstPath>,
+    mut world: ResMut<TypstWorld>,
+    mut changed: MessageWriter<FileChanged>,
+) {
+    for entity in removed.read() {
+        changed.write_batch(world.unexpose(entity).map(FileChanged));
+    }
+
+    for (entity, TypstPath(path), data) in query {
+        changed.write_batch(world.unexpose(entity).map(FileChanged));
+        changed.write(FileChanged(world.expose(entity, path, data.clone())));
+    }
+}
+
+/// Check whether any file read from disk has been modified since, such as
+/// while watching the site.
+pub fn poll(world: Res<TypstWorld>, mut changed: MessageWriter<FileChanged>) {
+    changed.write_batch(world.stale().into_iter().map(FileChanged));
+}
+
+/// Compile everything that depends on a changed file again, by marking what
+/// it was compiled from as changed.
+pub fn invalidate(
+    mut changed: MessageReader<FileChanged>,
+    world: Res<TypstWorld>,
+    mut math: Query<(&Dependencies, &mut Math)>,
+    mut diagrams: Query<(&Dependencies, &mut Diagram)>,
+    mut pages: Query<(Entity, &Dependencies, &mut Input<String>), With<TypstPage>>,
+    pdfs: Query<(&Dependencies, &PdfOf)>,
+    mut inputs: Query<&mut Inputs>,
+) {
+    let files: HashSet<FileId> = changed.read().map(|&FileChanged(id)| id).collect();
+
+    if files.is_empty() {
+        return;
+    }
+
+    for &id in &files {
+        world.reset(id);
+    }
+
+    for (dependencies, mut math) in &mut math {
+        if dependencies.any(&files) {
+            math.set_changed();
+        }
+    }
+
+    for (dependencies, mut diagram) in &mut diagrams {
+        if dependencies.any(&files) {
+            diagram.set_changed();
+        }
+    }
+
+    for (page, dependencies, mut contents) in &mut pages {
+        // NOTE: A page that reads its own source through `/@site` would be
+        //       compiled again every time it's compiled otherwise.
+        let invalidated = dependencies
+            .files
+            .intersection(&files)
+            .any(|&id| world.entity(id) != Some(page));
+
+        if invalidated {
+            contents.set_changed();
+        }
+    }
+
+    for (dependencies, &PdfOf(page)) in pdfs {
+        if dependencies.any(&files)
+            && let Ok(mut inputs) = inputs.get_mut(page)
+        {
+            inputs.set_changed();
+        }
+    }
+}
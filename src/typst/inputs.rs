@@ -0,0 +1,219 @@
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    query::{Changed, Or},
+    system::{Commands, Query, Res},
+};
+use chrono::{Datelike as _, NaiveDate};
+use typst::foundations::{Array, Datetime, Dict, Str, Value};
+
+use crate::{
+    content::{
+        page::{Created, Description, Extra, Keywords, Modified, Slug, Title},
+        route::Route,
+    },
+    site::Site,
+};
+
+/// The `sys.inputs` of everything compiled with typst for a page.
+///
+/// These are made up of two dictionaries:
+///
+/// - `site`, with the `title`, `description`, `url`, `language` and `author`
+///   of the site.
+/// - `page`, with the `title`, `description`, `slug`, `route`, `url`,
+///   `created`, `modified` and `keywords` of the page, as well as any fields
+///   within the `[extra]` table of its front matter as `extra`.
+///
+/// Such as, within a diagram:
+///
+/// ```typst
+/// Figure from #emph(sys.inputs.page.title)
+/// ```
+///
+/// Pages written in typst that are described by metadata rather than front
+/// matter are first compiled with an empty `page`, to read the metadata.
+#[derive(Debug, Clone, PartialEq, Hash, Default, Component)]
+pub struct Inputs(pub Dict);
+
+/// The metadata of a page, as exposed to typst.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageInputs<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    pub slug: Option<&'a str>,
+    pub route: Option<&'a Route>,
+    pub created: Option<NaiveDate>,
+    pub modified: Option<NaiveDate>,
+    pub keywords: &'a [String],
+    pub extra: Option<&'a toml::Table>,
+}
+
+impl Inputs {
+    /// Create the inputs of a page of `site`.
+    #[must_use]
+    pub fn new(site: &Site, page: PageInputs) -> Inputs {
+        let mut site_dict = Dict::new();
+
+        site_dict.insert("title".into(), string(&site.title));
+        site_dict.insert("description".into(), string(&site.description));
+        site_dict.insert("url".into(), string(site.url.as_str()));
+        site_dict.insert("language".into(), string(&site.language));
+        site_dict.insert("author".into(), string(&site.author.name));
+
+        let mut page_dict = Dict::new();
+
+        page_dict.insert("title".into(), string(page.title));
+        page_dict.insert("description".into(), string(page.description));
+        page_dict.insert("slug".into(), page.slug.map_or(Value::None, string));
+        page_dict.insert(
+            "route".into(),
+            page.route.map_or(Value::None, |route| string(&route.0)),
+        );
+        page_dict.insert(
+            "url".into(),
+            page.route
+                .map_or(Value::None, |route| string(route.url(&site.url).as_str())),
+        );
+        page_dict.insert("created".into(), date(page.created));
+        page_dict.insert("modified".into(), date(page.modified));
+        page_dict.insert(
+            "keywords".into(),
+            Value::Array(
+                page.keywords
+                    .iter()
+                    .map(|keyword| string(keyword))
+                    .collect(),
+            ),
+        );
+        page_dict.insert(
+            "extra".into(),
+            Value::Dict(page.extra.map(table).unwrap_or_default()),
+        );
+
+        let mut inputs = Dict::new();
+
+        inputs.insert("site".into(), Value::Dict(site_dict));
+        inputs.insert("page".into(), Value::Dict(page_dict));
+
+        Inputs(inputs)
+    }
+}
+
+/// Convert a string into a typst value.
+fn string(string: &str) -> Value {
+    Value::Str(string.into())
+}
+
+/// Convert a date into a typst value, or `none` if there isn't one.
+fn date(date: Option<NaiveDate>) -> Value {
+    date.and_then(|date| {
+        Datetime::from_ymd(
+            date.year(),
+            date.month().try_into().ok()?,
+            date.day().try_into().ok()?,
+        )
+    })
+    .map_or(Value::None, Value::Datetime)
+}
+
+/// Convert a TOML table into a typst dictionary.
+fn table(table: &toml::Table) -> Dict {
+    table
+        .iter()
+        .map(|(key, value)| (Str::from(key.as_str()), self::value(value)))
+        .collect()
+}
+
+/// Convert a TOML value into a typst value.
+fn value(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(value) => string(value),
+        toml::Value::Integer(integer) => Value::Int(*integer),
+        toml::Value::Float(float) => Value::Float(*float),
+        toml::Value::Boolean(boolean) => Value::Bool(*boolean),
+        toml::Value::Datetime(datetime) => {
+            let date = datetime.date;
+            let time = datetime.time;
+
+            let converted = match (date, time) {
+                (Some(date), Some(time)) => Datetime::from_ymd_hms(
+                    date.year.into(),
+                    date.month,
+                    date.day,
+                    time.hour,
+                    time.minute,
+                    time.second,
+                ),
+                (Some(date), None) => Datetime::from_ymd(date.year.into(), date.month, date.day),
+                (None, Some(time)) => Datetime::from_hms(time.hour, time.minute, time.second),
+                (None, None) => None,
+            };
+
+            // NOTE: Typst has no notion of offsets, so anything it can't
+            //       represent is passed along as a string instead.
+            match converted {
+                Some(converted) if datetime.offset.is_none() => Value::Datetime(converted),
+                _ => string(&datetime.to_string()),
+            }
+        }
+        toml::Value::Array(array) => Value::Array(array.iter().map(self::value).collect::<Array>()),
+        toml::Value::Table(table) => Value::Dict(self::table(table)),
+    }
+}
+
+/// Expose the metadata of every page, as well as the introduction of every
+/// collection, to typst.
+pub fn assign(
+    query: Query<
+        (
+            Entity,
+            &Title,
+            &Description,
+            Option<&Keywords>,
+            &Route,
+            Option<&Slug>,
+            Option<&Created>,
+            Option<&Modified>,
+            Option<&Extra>,
+        ),
+        Or<(
+            Changed<Title>,
+            Changed<Description>,
+            Changed<Keywords>,
+            Changed<Route>,
+            Changed<Extra>,
+        )>,
+    >,
+    site: Res<Site>,
+    mut commands: Commands,
+) {
+    for (
+        entity,
+        Title(title),
+        Description(description),
+        keywords,
+        route,
+        slug,
+        created,
+        modified,
+        extra,
+    ) in query
+    {
+        let inputs = Inputs::new(
+            &site,
+            PageInputs {
+                title,
+                description,
+                slug: slug.map(|Slug(slug)| slug.as_str()),
+                route: Some(route),
+                created: created.map(|created| created.0),
+                modified: modified.map(|modified| modified.0),
+                keywords: keywords.map_or(&[], |Keywords(keywords)| keywords),
+                extra: extra.map(|Extra(extra)| extra),
+            },
+        );
+
+        commands.entity(entity).insert(inputs);
+    }
+}
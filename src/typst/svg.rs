@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+
+use typst::layout::Page;
+
+/// The colours typst draws with by default, as the attribute they're set by
+/// and the ways they're spelled, along with the value they're replaced with,
+/// so that SVGs follow the theme of the site.
+///
+/// Presentation attributes can't refer to custom properties, so these are set
+/// through the `style` attribute instead, falling back to the colour of the
+/// surrounding text, and to white, wherever the theme isn't around, such as
+/// within EPUBs.
+const COLORS: &[(&str, &[&str], &str)] = &[
+    (
+        "fill",
+        &["#000000", "#000000ff", "#000"],
+        "var(--fg, currentColor)",
+    ),
+    (
+        "stroke",
+        &["#000000", "#000000ff", "#000"],
+        "var(--fg, currentColor)",
+    ),
+    (
+        "fill",
+        &["#ffffff", "#ffffffff", "#fff"],
+        "var(--bg, #ffffff)",
+    ),
+];
+
+/// Render `page` to an SVG that follows the theme of the site.
+#[must_use]
+pub fn svg(page: &Page) -> String {
+    theme(&typst_svg::svg(page))
+}
+
+/// Replace the foreground and background colours of an SVG made by typst
+/// with those of the active theme, see `_everforest.scss`.
+///
+/// Anything drawn in black takes on the foreground colour, and anything drawn
+/// in white takes on the background.
+#[must_use]
+pub fn theme(svg: &str) -> String {
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+
+    while let Some(start) = rest.find('<')
+        && let Some(end) = rest[start..].find('>')
+    {
+        let end = start + end + 1;
+
+        out.push_str(&rest[..start]);
+        out.push_str(&element(&rest[start..end]));
+        rest = &rest[end..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Replace the colours set on the start tag `tag`, merging them into its
+/// `style` attribute if it already has one.
+fn element(tag: &str) -> Cow<'_, str> {
+    let mut tag = Cow::Borrowed(tag);
+    let mut declarations = Vec::new();
+
+    for &(property, colors, to) in COLORS {
+        for color in colors {
+            let attribute = format!(r#" {property}="{color}""#);
+
+            if tag.contains(&attribute) {
+                tag = Cow::Owned(tag.replace(&attribute, ""));
+                declarations.push(format!("{property}: {to}"));
+            }
+        }
+    }
+
+    if declarations.is_empty() {
+        return tag;
+    }
+
+    let mut tag = tag.into_owned();
+    let declarations = declarations.join("; ");
+
+    // NOTE: Anything already within `style` comes last, so it still takes
+    // precedence, as it would have over the presentation attributes.
+    match tag.find(r#" style=""#) {
+        Some(index) => {
+            tag.insert_str(index + r#" style=""#.len(), &format!("{declarations}; "));
+        }
+        None => {
+            let end = tag.len() - if tag.ends_with("/>") { 2 } else { 1 };
+
+            tag.insert_str(end, &format!(r#" style="{declarations}""#));
+        }
+    }
+
+    Cow::Owned(tag)
+}
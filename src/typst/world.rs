@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{
+    ecs::{
+        entity::Entity,
+        query::Changed,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    log::warn,
+};
+use chrono::Datelike as _;
+use parking_lot::Mutex;
+use typst::{
+    Feature, Library, LibraryExt as _, World,
+    diag::{FileError, FileResult, SourceResult, Warned},
+    foundations::{Bytes, Datetime},
+    layout::PagedDocument,
+    syntax::{FileId, Source, VirtualPath},
+    text::{Font, FontBook},
+    utils::{LazyHash, hash128},
+};
+use typst_html::HtmlDocument;
+use typst_kit::{
+    download::Downloader,
+    fonts::{FontSearcher, FontSlot},
+    package::PackageStorage,
+};
+
+use crate::{
+    Now,
+    typst::{
+        files::{Dependencies, FileData, FileEntry, Files},
+        inputs::Inputs,
+        svg,
+    },
+};
+
+/// The typst world shared by everything compiled with typst.
+#[derive(Resource)]
+pub struct TypstWorld {
+    /// The root directory files are resolved relative to.
+    root: PathBuf,
+    /// The libraries built so far, keyed by the hash of their inputs, as
+    /// every page has inputs of its own, along with the age they were last
+    /// used at.
+    libraries: Mutex<HashMap<u128, (usize, Arc<LazyHash<Library>>)>>,
+    /// The amount of times caches have been evicted, see [`evict`].
+    age: usize,
+    book: LazyHash<FontBook>,
+    fonts: Vec<FontSlot>,
+    package_storage: PackageStorage,
+    files: Files,
+    /// The entities exposed as files, keyed by the id of their file.
+    entities: HashMap<FileId, Entity>,
+    /// The id and data of the file exposed by each entity.
+    entity_files: HashMap<Entity, (FileId, FileData)>,
+    /// The sources parsed so far, so that they needn't be parsed again.
+    sources: Mutex<HashMap<FileId, Source>>,
+    today: Option<Datetime>,
+}
+
+impl TypstWorld {
+    /// Create a new world rooted at `root`.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>, now: &Now) -> TypstWorld {
+        let fonts = FontSearcher::new().include_system_fonts(true).search();
+        let date = now.0.date_naive();
+
+        TypstWorld {
+            root: root.into(),
+            libraries: Mutex::new(HashMap::new()),
+            age: 0,
+            book: LazyHash::new(fonts.book),
+            fonts: fonts.fonts,
+            package_storage: PackageStorage::new(
+                None,
+                None,
+                Downloader::new(concat!("glados/", env!("CARGO_PKG_VERSION"))),
+            ),
+            files: Files::default(),
+            entities: HashMap::new(),
+            entity_files: HashMap::new(),
+            sources: Mutex::new(HashMap::new()),
+            today: Datetime::from_ymd(
+                date.year(),
+                date.month().try_into().unwrap_or(1),
+                date.day().try_into().unwrap_or(1),
+            ),
+        }
+    }
+
+    /// The root directory files are resolved relative to.
+    #[inline]
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The standard library, with `inputs` as its `sys.inputs`.
+    fn library(&self, Inputs(inputs): &Inputs) -> Arc<LazyHash<Library>> {
+        let mut libraries = self.libraries.lock();
+        let (age, library) = libraries.entry(hash128(inputs)).or_insert_with(|| {
+            let library = Library::builder()
+                .with_features([Feature::Html].into_iter().collect())
+                .with_inputs(inputs.clone())
+                .build();
+
+            (self.age, Arc::new(LazyHash::new(library)))
+        });
+
+        *age = self.age;
+        library.clone()
+    }
+
+    /// Forget the libraries that haven't been used within the last `max_age`
+    /// evictions.
+    fn evict(&mut self, max_age: usize) {
+        self.age += 1;
+
+        let age = self.age;
+
+        self.libraries
+            .get_mut()
+            .retain(|_, (used, _)| age - *used <= max_age);
+    }
+
+    /// Read the file `id`.
+    fn read(&self, id: FileId) -> FileResult<Bytes> {
+        let mut buf = PathBuf::new();
+        let loc = Files::resolve(
+            id,
+            &self.root,
+            &self.package_storage,
+            &mut |id| self.entities.get(&id).copied(),
+            &mut buf,
+        )?;
+
+        let mut files = self.files.0.lock();
+        let entry: &mut FileEntry = files.entry(id).or_default();
+
+        entry
+            .get_or_init(loc, &mut |entity| {
+                self.entity_files.get(&entity).map(|(_, data)| data.clone())
+            })
+            .clone()
+            .map(|data| Bytes::new(data.into_binary().into_owned()))
+    }
+
+    /// Expose the data of `entity` as the file at `path`, replacing whatever
+    /// was there before, and returning the id of the file.
+    pub fn expose(&mut self, entity: Entity, path: &str, data: FileData) -> FileId {
+        let id = FileId::new(None, VirtualPath::new(path));
+
+        if let Some(&other) = self.entities.get(&id)
+            && other != entity
+        {
+            warn!("{path:?} is exposed to typst by more than one entity");
+        }
+
+        // NOTE: Anything read from the file before is stale now.
+        self.reset(id);
+
+        self.entities.insert(id, entity);
+        self.entity_files.insert(entity, (id, data));
+
+        id
+    }
+
+    /// Stop exposing the file exposed by `entity`, if any, returning its id.
+    pub fn unexpose(&mut self, entity: Entity) -> Option<FileId> {
+        let (id, _) = self.entity_files.remove(&entity)?;
+
+        // NOTE: Another entity may have taken over the file since.
+        if self.entities.get(&id) == Some(&entity) {
+            self.entities.remove(&id);
+        }
+
+        self.reset(id);
+
+        Some(id)
+    }
+
+    /// Returns the entity that exposes the file `id`, if any.
+    #[must_use]
+    pub fn entity(&self, id: FileId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+    /// Mark the file `id` to be read again the next time it's accessed,
+    /// forgetting anything parsed from it.
+    pub fn reset(&self, id: FileId) {
+        if let Some(entry) = self.files.0.lock().get_mut(&id) {
+            entry.reset();
+        }
+
+        self.sources.lock().remove(&id);
+    }
+
+    /// Returns the files read from disk that have been modified since.
+    #[must_use]
+    pub fn stale(&self) -> Vec<FileId> {
+        self.files
+            .0
+            .lock()
+            .iter()
+            .filter(|(_, entry)| entry.is_stale())
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Create the source of a document, as if it were a file at `path`
+    /// within the root.
+    #[must_use]
+    pub fn source(path: &str, text: String) -> Source {
+        Source::new(FileId::new_fake(VirtualPath::new(path)), text)
+    }
+
+    /// Create the source of a document from the file at `path`, relative to
+    /// the root.
+    #[must_use]
+    pub fn file_source(path: &Path, text: String) -> Source {
+        Source::new(FileId::new(None, VirtualPath::new(path)), text)
+    }
+
+    /// Create a document to compile `main` with, with `inputs` as its
+    /// `sys.inputs`.
+    fn document(&self, main: &Source, inputs: &Inputs) -> Document<'_> {
+        Document {
+            world: self,
+            library: self.library(inputs),
+            main: main.clone(),
+            dependencies: Mutex::new(Dependencies::default()),
+        }
+    }
+
+    /// Compile `main` to a document, with `inputs` as its `sys.inputs`.
+    pub fn compile(&self, main: &Source, inputs: &Inputs) -> Compiled<PagedDocument> {
+        let document = self.document(main, inputs);
+        let warned = typst::compile::<PagedDocument>(&document);
+
+        Compiled {
+            warned,
+            dependencies: document.dependencies(),
+        }
+    }
+
+    /// Compile `main` to an SVG of its first page.
+    pub fn compile_svg(&self, main: &Source, inputs: &Inputs) -> Compiled<String> {
+        self.compile(main, inputs)
+            .map(|document| document.pages.first().map(svg::svg).unwrap_or_default())
+    }
+
+    /// Compile `main` to HTML, with typst's HTML export.
+    pub fn compile_html(&self, main: &Source, inputs: &Inputs) -> Compiled<String> {
+        let document = self.document(main, inputs);
+        let warned = typst::compile::<HtmlDocument>(&document);
+
+        Compiled {
+            warned,
+            dependencies: document.dependencies(),
+        }
+        .and_then(|document| typst_html::html(&document))
+    }
+}
+
+/// The result of compiling something with typst.
+#[derive(Debug, Clone)]
+pub struct Compiled<T> {
+    /// The output, and any warnings.
+    pub warned: Warned<SourceResult<T>>,
+    /// The files accessed while compiling.
+    pub dependencies: Dependencies,
+}
+
+impl<T> Compiled<T> {
+    /// Map the output, if there is one.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Compiled<U> {
+        self.and_then(|output| Ok(f(output)))
+    }
+
+    /// Map the output, if there is one, with something that may fail.
+    pub fn and_then<U>(self, f: impl FnOnce(T) -> SourceResult<U>) -> Compiled<U> {
+        let Warned { output, warnings } = self.warned;
+
+        Compiled {
+            warned: Warned {
+                output: output.and_then(f),
+                warnings,
+            },
+            dependencies: self.dependencies,
+        }
+    }
+}
+
+/// A single document compiled within a [`TypstWorld`].
+pub struct Document<'w> {
+    pub world: &'w TypstWorld,
+    /// The standard library, with the `sys.inputs` of the document.
+    pub library: Arc<LazyHash<Library>>,
+    /// The source of the document.
+    pub main: Source,
+    /// The files that have been accessed so far.
+    pub dependencies: Mutex<Dependencies>,
+}
+
+impl Document<'_> {
+    /// Record that the file `id` has been accessed.
+    fn depend(&self, id: FileId) {
+        self.dependencies.lock().files.insert(id);
+    }
+
+    /// The files that have been accessed.
+    fn dependencies(&self) -> Dependencies {
+        self.dependencies.lock().clone()
+    }
+}
+
+impl World for Document<'_> {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        &self.world.book
+    }
+
+    fn main(&self) -> FileId {
+        self.main.id()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            return Ok(self.main.clone());
+        }
+
+        self.depend(id);
+
+        if let Some(source) = self.world.sources.lock().get(&id) {
+            return Ok(source.clone());
+        }
+
+        let bytes = self.world.read(id)?;
+        let text = std::str::from_utf8(&bytes).map_err(|_| FileError::InvalidUtf8)?;
+        let source = Source::new(id, text.into());
+
+        self.world.sources.lock().insert(id, source.clone());
+
+        Ok(source)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.depend(id);
+        self.world.read(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.world.fonts.get(index)?.get()
+    }
+
+    fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
+        self.world.today
+    }
+}
+
+/// Set up the typst world, rooted at the current directory.
+pub fn init(now: Res<Now>, mut commands: Commands) {
+    commands.insert_resource(TypstWorld::new(".", &now));
+}
+
+/// The amount of builds something typst cached survives without being used.
+const CACHE_AGE: usize = 10;
+
+/// Evict anything typst cached that hasn't been used in a while, so that
+/// memory doesn't grow without bound while watching the site.
+///
+/// Caches only age when something has been compiled, so that they survive
+/// while nothing changes.
+pub fn evict(compiled: Query<(), Changed<Dependencies>>, mut world: ResMut<TypstWorld>) {
+    if compiled.is_empty() {
+        return;
+    }
+
+    comemo::evict(CACHE_AGE);
+    world.evict(CACHE_AGE);
+}
@@ -1,3 +1,7 @@
+use std::fmt::Write as _;
+
+use maud::Escaper;
+
 /// Overwrite the data in `dest`, neglecting to execute it's destructor.
 #[inline(always)]
 pub const fn write<T>(dest: &mut T, src: T) {
@@ -5,3 +9,57 @@ pub const fn write<T>(dest: &mut T, src: T) {
     //          valid for writes.
     unsafe { (&raw mut *dest).write(src) }
 }
+
+/// Escape `input` for use within HTML or XML, appending it to `output`.
+#[inline]
+pub fn escape(input: &str, output: &mut String) {
+    // NOTE: Writing to an `Escaper` never fails.
+    let _ = Escaper::new(output).write_str(input);
+}
+
+/// Returns `input` escaped for use within HTML or XML.
+#[inline]
+#[must_use]
+pub fn escaped(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    escape(input, &mut output);
+
+    output
+}
+
+/// Normalize `input` into a slug.
+///
+/// The input is lowercased, and any run of characters that aren't alphanumeric
+/// is collapsed into a single `-`.
+#[must_use]
+pub fn slugify(input: &str) -> String {
+    let mut slug = String::with_capacity(input.len());
+
+    for c in input.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+
+    slug
+}
+
+/// Returns the line and column of the byte at `offset` within `text`, both
+/// starting at 1.
+///
+/// Columns are counted in characters rather than bytes.
+#[must_use]
+pub fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..text.floor_char_boundary(offset.min(text.len()))];
+    let line = before.matches('\n').count() + 1;
+    let start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    (line, before[start..].chars().count() + 1)
+}